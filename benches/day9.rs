@@ -0,0 +1,55 @@
+//! Times `Rope::apply_instructions` on a large spiral with each way of recording visited cells.
+//!
+//! Run with `cargo bench --bench day9`.
+
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use aoc2022::day9::{input_generator, Bounds, CellSet, Instruction, Position, Rope, Visited};
+
+const RUNS: usize = 5;
+
+/// `Visited` ignoring the box hint, so that it stays a sparse bitset.
+#[derive(Default)]
+struct Sparse(Visited);
+
+impl CellSet for Sparse {
+    fn insert(&mut self, pos: Position) -> bool {
+        self.0.insert(pos)
+    }
+
+    fn contains(&self, pos: &Position) -> bool {
+        self.0.contains(pos)
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn reserve(&mut self, _bounds: Bounds) {}
+}
+
+/// Best time of `RUNS` runs of `apply_instructions` with `S`, along with the number of cells visited.
+fn time<S: CellSet + Default>(instructions: &[Instruction]) -> (Duration, usize) {
+    (0..RUNS).map(|_| {
+        let start = Instant::now();
+        let mut rope = Rope::with_cells(10, 9, S::default());
+        rope.apply_instructions(instructions);
+        (start.elapsed(), rope.visited().len())
+    }).min().unwrap()
+}
+
+fn main() {
+    let spiral = (1..=3000).map(|i| format!("{} {}", ["R", "U", "L", "D"][i % 4], i)).collect::<Vec<String>>();
+    let instructions = input_generator(&spiral.join("\n")).unwrap();
+
+    let results = [
+        ("HashSet", time::<HashSet<Position>>(&instructions)),
+        ("sparse bitset", time::<Sparse>(&instructions)),
+        ("dense bitset", time::<Visited>(&instructions)),
+    ];
+    assert!(results.iter().all(|(_, (_, cells))| *cells == results[0].1.1));
+    println!("{} steps, {} cells visited by the tail", (1..=3000).sum::<usize>(), results[0].1.1);
+    for (name, (elapsed, _)) in results {
+        println!("{:>14}: {:?}", name, elapsed);
+    }
+}
@@ -1,16 +1,17 @@
 use aoc_runner_derive::{aoc, aoc_generator};
+use crate::parse::{parse, ParseError};
 
 #[aoc_generator(day1)]
-pub fn input_generator(input: &str) -> Vec<Vec<u32>> {
+pub fn input_generator(input: &str) -> Result<Vec<Vec<u32>>, ParseError> {
     let mut ret = vec![vec![]];
-    for l in input.lines() {
+    for (i, l) in input.lines().enumerate() {
         let s = l.trim();
         match s {
             "" => ret.push(vec![]),
-            _ => ret.last_mut().unwrap().push(s.parse().unwrap())
+            _ => ret.last_mut().unwrap().push(parse(i, l, s, "a calorie count")?)
         }
     }
-    return ret;
+    Ok(ret)
 }
 
 #[aoc(day1, part1)]
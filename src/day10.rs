@@ -1,51 +1,476 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 use aoc_runner_derive::{aoc, aoc_generator};
+use crate::grid::{Grid, Position};
+use crate::image::{pbm, pgm_sequence, scale};
+use crate::ocr::{recognize, OcrError};
+use crate::parse::{next_token, parse, ParseError};
 
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Instruction {
     Addx(i8),
     Noop,
 }
 
-#[derive(Clone)]
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instruction::Addx(i) => write!(f, "addx {}", i),
+            Instruction::Noop => write!(f, "noop"),
+        }
+    }
+}
+
+impl Instruction {
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Addx(_) => "addx",
+            Instruction::Noop => "noop",
+        }
+    }
+
+    /// Applies the effect of the instruction, once its last cycle has completed.
+    fn execute(&self, registers: &mut Registers) {
+        match *self {
+            Instruction::Noop => (),
+            Instruction::Addx(i) => registers.x += i as isize,
+        }
+    }
+}
+
+/// A line of assembly: `[label:] [instruction] [; comment]`, every part being optional.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Line {
+    pub label: Option<String>,
+    pub instruction: Option<Instruction>,
+    pub comment: Option<String>,
+}
+
+impl Display for Line {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut parts = vec![];
+        if let Some(label) = &self.label {
+            parts.push(format!("{}:", label));
+        }
+        if let Some(instruction) = &self.instruction {
+            parts.push(instruction.to_string());
+        }
+        if let Some(comment) = &self.comment {
+            parts.push(format!("; {}", comment));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// An assembly listing, printing it gives back its canonical source.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Program {
+    pub lines: Vec<Line>,
+}
+
+impl Program {
+    pub fn assemble(source: &str) -> Result<Program, ParseError> {
+        let mut labels = HashSet::new();
+        let lines = source.lines().enumerate().map(|(i, l)| {
+            let line = parse_line(i, l)?;
+            if let Some(label) = &line.label {
+                if !labels.insert(label.clone()) {
+                    let token = &l[l.find(label.as_str()).unwrap()..][..label.len()];
+                    return Err(ParseError::new("a label not defined yet", token).at_token(i, l, token));
+                }
+            }
+            Ok(line)
+        }).collect::<Result<Vec<Line>, ParseError>>()?;
+        Ok(Program { lines })
+    }
+
+    pub fn instructions(&self) -> impl Iterator<Item=&Instruction> {
+        self.lines.iter().filter_map(|l| l.instruction.as_ref())
+    }
+
+    /// Index of the instruction following each label.
+    pub fn labels(&self) -> HashMap<&str, usize> {
+        let mut ret = HashMap::new();
+        let mut idx = 0;
+        for line in &self.lines {
+            if let Some(label) = &line.label {
+                ret.insert(label.as_str(), idx);
+            }
+            if line.instruction.is_some() {
+                idx += 1;
+            }
+        }
+        ret
+    }
+}
+
+impl Display for Program {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, line) in self.lines.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_line(idx: usize, l: &str) -> Result<Line, ParseError> {
+    let mut line = Line::default();
+    let code = match l.split_once(';') {
+        Some((code, comment)) => {
+            line.comment = Some(comment.trim().to_owned());
+            code
+        }
+        None => l
+    };
+    let mut tokens = code.split_whitespace().peekable();
+    if let Some(label) = tokens.peek().and_then(|t| t.strip_suffix(':')) {
+        let mut chars = label.chars();
+        let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(ParseError::new("a label made of letters, digits and `_`", label).at_token(idx, l, label));
+        }
+        line.label = Some(label.to_owned());
+        tokens.next();
+    }
+    if let Some(mnemonic) = tokens.next() {
+        line.instruction = Some(match mnemonic {
+            "addx" => Instruction::Addx(parse(idx, l, next_token(&mut tokens, idx, l, "an operand")?, "an 8-bit integer")?),
+            "noop" => Instruction::Noop,
+            _ => return Err(ParseError::new("`addx` or `noop`", mnemonic).at_token(idx, l, mnemonic))
+        });
+    }
+    if let Some(extra) = tokens.next() {
+        return Err(ParseError::new("end of line", extra).at_token(idx, l, extra));
+    }
+    Ok(line)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Registers {
+    pub x: isize,
+}
+
+impl Default for Registers {
+    fn default() -> Self {
+        Registers { x: 1 }
+    }
+}
+
+/// Number of cycles each instruction takes, by mnemonic.
+#[derive(Clone, Debug)]
+pub struct InstructionTable {
+    costs: HashMap<&'static str, usize>,
+}
+
+impl Default for InstructionTable {
+    fn default() -> Self {
+        InstructionTable { costs: HashMap::from([("addx", 2), ("noop", 1)]) }
+    }
+}
+
+impl InstructionTable {
+    pub fn with_cost(mut self, mnemonic: &'static str, cycles: usize) -> Self {
+        assert!(cycles > 0, "`{}` must take at least one cycle", mnemonic);
+        self.costs.insert(mnemonic, cycles);
+        self
+    }
+
+    pub fn cost(&self, instruction: &Instruction) -> usize {
+        self.costs[instruction.mnemonic()]
+    }
+}
+
+/// Hooks called by `Cpu` on every cycle, cycles are numbered from 1.
+pub trait Observer {
+    /// Called while `cycle` runs, `registers` hold the value seen during the cycle.
+    fn during(&mut self, _cycle: usize, _registers: &Registers) {}
+
+    /// Called once `cycle` has completed, with the effects of finished instructions applied.
+    fn after(&mut self, _cycle: usize, _registers: &Registers) {}
+}
+
+impl<A: Observer, B: Observer> Observer for (A, B) {
+    fn during(&mut self, cycle: usize, registers: &Registers) {
+        self.0.during(cycle, registers);
+        self.1.during(cycle, registers);
+    }
+
+    fn after(&mut self, cycle: usize, registers: &Registers) {
+        self.0.after(cycle, registers);
+        self.1.after(cycle, registers);
+    }
+}
+
+impl Observer for () {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Stop {
+    Halted,
+    Breakpoint(usize),
+}
+
+pub struct Cpu<'a> {
+    program: &'a [Instruction],
+    table: InstructionTable,
+    registers: Registers,
+    cycle: usize,
+    pc: usize,
+    elapsed: usize,
+    breakpoints: HashSet<usize>,
+}
+
+impl<'a> Cpu<'a> {
+    pub fn new(program: &'a [Instruction]) -> Self {
+        Self::with_table(program, InstructionTable::default())
+    }
+
+    pub fn with_table(program: &'a [Instruction], table: InstructionTable) -> Self {
+        Cpu {
+            program,
+            table,
+            registers: Registers::default(),
+            cycle: 0,
+            pc: 0,
+            elapsed: 0,
+            breakpoints: HashSet::new(),
+        }
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    /// Number of completed cycles.
+    pub fn cycle(&self) -> usize {
+        self.cycle
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.pc >= self.program.len()
+    }
+
+    /// Makes `run` stop once `cycle` has completed.
+    pub fn add_breakpoint(&mut self, cycle: usize) {
+        self.breakpoints.insert(cycle);
+    }
+
+    /// Runs a single cycle, returning its number or `None` if the program has ended.
+    pub fn step(&mut self, observer: &mut impl Observer) -> Option<usize> {
+        let instruction = self.program.get(self.pc)?;
+        self.cycle += 1;
+        observer.during(self.cycle, &self.registers);
+        self.elapsed += 1;
+        if self.elapsed == self.table.cost(instruction) {
+            instruction.execute(&mut self.registers);
+            self.pc += 1;
+            self.elapsed = 0;
+        }
+        observer.after(self.cycle, &self.registers);
+        Some(self.cycle)
+    }
+
+    /// Runs until the program ends or a breakpoint is reached.
+    pub fn run(&mut self, observer: &mut impl Observer) -> Stop {
+        while let Some(cycle) = self.step(observer) {
+            if self.breakpoints.contains(&cycle) {
+                return Stop::Breakpoint(cycle);
+            }
+        }
+        Stop::Halted
+    }
+}
+
+/// Screen geometry and sampled cycles of the communication device.
+///
+/// Only `CrtConfig::new` can set the width, so a config never describes an empty row of pixels.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CrtConfig {
+    width: usize,
+    pub height: usize,
+    /// Width of the sprite, which starts `(sprite_width - 1) / 2` pixels left of `x`.
+    pub sprite_width: usize,
+    pub sample_start: usize,
+    /// Cycles between two samples, 0 sampling `sample_start` only once.
+    pub sample_interval: usize,
+    pub sample_count: usize,
+}
+
+impl Default for CrtConfig {
+    fn default() -> Self {
+        CrtConfig {
+            width: 40,
+            height: 6,
+            sprite_width: 3,
+            sample_start: 20,
+            sample_interval: 40,
+            sample_count: 6,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CrtConfigError {
+    /// The screen is 0 pixels wide, leaving nowhere to draw any cycle.
+    ZeroWidth,
+}
+
+impl Display for CrtConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CrtConfigError::ZeroWidth => f.write_str("the screen must be at least one pixel wide"),
+        }
+    }
+}
+
+impl Error for CrtConfigError {}
+
+impl CrtConfig {
+    /// A `width` x `height` screen, sprite and sampled cycles being the puzzle ones.
+    pub fn new(width: usize, height: usize) -> Result<Self, CrtConfigError> {
+        if width == 0 {
+            return Err(CrtConfigError::ZeroWidth);
+        }
+        Ok(CrtConfig { width, height, ..CrtConfig::default() })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn sampled_cycles(&self) -> impl Iterator<Item=usize> + '_ {
+        (0..self.sample_count).map(|i| self.sample_start + i * self.sample_interval)
+    }
+
+    fn sprite_covers(&self, x: isize, col: usize) -> bool {
+        let start = x - (self.sprite_width as isize - 1) / 2;
+        (start..start + self.sprite_width as isize).contains(&(col as isize))
+    }
+}
+
+/// Sums the signal strength, i.e. `cycle * x`, during the sampled cycles.
+#[derive(Default)]
+pub struct SignalStrength {
+    pub config: CrtConfig,
+    pub total: isize,
+}
+
+impl SignalStrength {
+    pub fn new(config: CrtConfig) -> Self {
+        SignalStrength { config, total: 0 }
+    }
+}
+
+impl Observer for SignalStrength {
+    fn during(&mut self, cycle: usize, registers: &Registers) {
+        if self.config.sampled_cycles().any(|c| c == cycle) {
+            self.total += cycle as isize * registers.x
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Pixel {
     On,
     Off,
 }
 
-impl ToString for Pixel {
-    fn to_string(&self) -> String {
-        match self {
+impl Display for Pixel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
             Pixel::On => "#",
             Pixel::Off => "."
-        }.to_owned()
+        })
     }
 }
 
-#[aoc_generator(day10)]
-pub fn input_generator(input: &str) -> Vec<Instruction> {
-    input.lines().map(|l| {
-        let split = l.trim().split(" ").collect::<Vec<&str>>();
-        match split[0] {
-            "addx" => Instruction::Addx(split[1].parse().unwrap()),
-            "noop" => Instruction::Noop,
-            _ => unreachable!()
+/// Draws a pixel on every cycle, lit when the sprite positioned by `x` covers it.
+pub struct Crt {
+    config: CrtConfig,
+    pub screen: Vec<Vec<Pixel>>,
+}
+
+impl Default for Crt {
+    fn default() -> Self {
+        Crt::new(CrtConfig::default())
+    }
+}
+
+impl Crt {
+    pub fn new(config: CrtConfig) -> Self {
+        Crt {
+            screen: vec![vec![Pixel::Off; config.width]; config.height],
+            config,
         }
-    }).collect()
+    }
+
+    pub fn config(&self) -> &CrtConfig {
+        &self.config
+    }
+
+    /// The screen as a bitmap, lit pixels being `true`.
+    pub fn bitmap(&self) -> Grid<bool> {
+        let cells = self.screen.iter().flatten().map(|p| *p == Pixel::On).collect();
+        Grid::new(self.config.width, self.screen.len(), cells)
+    }
+
+    /// Raw PBM export of the screen, lit pixels in black, each drawn as a `factor` pixels square.
+    pub fn to_pbm(&self, factor: usize) -> Vec<u8> {
+        pbm(&scale(&self.bitmap(), factor))
+    }
 }
 
-fn build_cycles(program: &[Instruction]) -> Vec<isize> {
-    let mut cycles = vec![];
-    let mut x = 1;
-    program.iter().for_each(|inst| {
-        cycles.push(x);
-        match *inst {
-            Instruction::Noop => (),
-            Instruction::Addx(i) => {
-                cycles.push(x);
-                x += i as isize;
-            }
+impl Observer for Crt {
+    fn during(&mut self, cycle: usize, registers: &Registers) {
+        let (y, x) = ((cycle - 1) / self.config.width, (cycle - 1) % self.config.width);
+        if y < self.screen.len() && self.config.sprite_covers(registers.x, x) {
+            self.screen[y][x] = Pixel::On
+        }
+    }
+}
+
+/// Grey level of the pixel being drawn in `Animation` frames.
+const BEAM: u8 = 128;
+
+/// Records a greyscale frame of the screen on every cycle, showing how the beam draws it.
+#[derive(Default)]
+pub struct Animation {
+    pub crt: Crt,
+    pub frames: Vec<Grid<u8>>,
+}
+
+impl Animation {
+    pub fn new(config: CrtConfig) -> Self {
+        Animation { crt: Crt::new(config), frames: vec![] }
+    }
+
+    /// All the frames as a single PGM stream, each pixel drawn as a `factor` pixels square.
+    pub fn to_pgm(&self, factor: usize) -> Vec<u8> {
+        let frames = self.frames.iter().map(|f| scale(f, factor)).collect::<Vec<Grid<u8>>>();
+        pgm_sequence(&frames)
+    }
+}
+
+impl Observer for Animation {
+    fn during(&mut self, cycle: usize, registers: &Registers) {
+        self.crt.during(cycle, registers);
+        let mut frame = self.crt.bitmap().map(|&on| if on { 0 } else { 255 });
+        let width = self.crt.config.width;
+        let beam = Position::new((cycle - 1) / width, (cycle - 1) % width);
+        if let Some(p) = frame.get_mut(beam) {
+            *p = BEAM
         }
-    });
-    cycles
+        self.frames.push(frame)
+    }
+}
+
+#[aoc_generator(day10)]
+pub fn input_generator(input: &str) -> Result<Vec<Instruction>, ParseError> {
+    Ok(Program::assemble(input)?.instructions().cloned().collect())
 }
 
 fn print_crt_line(line: &[Pixel]) -> String {
@@ -61,27 +486,31 @@ fn print_crt(crt: &[Vec<Pixel>]) -> String {
         .collect::<Vec<String>>().join("\n")
 }
 
+pub fn signal_strength(input: &[Instruction], config: &CrtConfig) -> isize {
+    let mut signal = SignalStrength::new(config.clone());
+    Cpu::new(input).run(&mut signal);
+    signal.total
+}
+
+pub fn draw(input: &[Instruction], config: &CrtConfig) -> Crt {
+    let mut crt = Crt::new(config.clone());
+    Cpu::new(input).run(&mut crt);
+    crt
+}
+
 #[aoc(day10, part1)]
 pub fn solve_part1(input: &[Instruction]) -> isize {
-    let cycles = build_cycles(input);
-    [20usize, 60, 100, 140, 180, 220].iter().map(|&i| (i as isize) * cycles[i - 1]).sum()
+    signal_strength(input, &CrtConfig::default())
 }
 
 #[aoc(day10, part2)]
 pub fn solve_part2(input: &[Instruction]) -> String {
-    let mut crt = vec![vec![Pixel::Off; 40]; 6];
-    let cycles = build_cycles(input);
-
-    for (i, register) in cycles.iter().enumerate() {
-        let sprite_pos = *register as usize % 40;
-        let y = i / 40;
-        if sprite_pos.abs_diff(i % 40) <= 1 {
-            crt[y as usize][i % 40] = Pixel::On
-        }
-    }
+    print_crt(&draw(input, &CrtConfig::default()).screen)
+}
 
-    let ret = print_crt(&crt);
-    ret
+#[aoc(day10, part2, ocr)]
+pub fn solve_part2_ocr(input: &[Instruction]) -> Result<String, OcrError> {
+    recognize(&draw(input, &CrtConfig::default()).screen)
 }
 
 #[cfg(test)]
@@ -240,26 +669,162 @@ noop
 noop
 noop";
 
+    #[derive(Default)]
+    struct Recorder {
+        during: Vec<isize>,
+        after: Vec<isize>,
+    }
+
+    impl Observer for Recorder {
+        fn during(&mut self, _cycle: usize, registers: &Registers) {
+            self.during.push(registers.x)
+        }
+
+        fn after(&mut self, _cycle: usize, registers: &Registers) {
+            self.after.push(registers.x)
+        }
+    }
+
     #[test]
     fn cycles() {
-        let cycles = build_cycles(&input_generator(TEST_INPUT));
-        println!("{:?}", cycles);
-        assert_eq!(cycles[0], 1);
-        assert_eq!(cycles[1], 1);
-        assert_eq!(cycles[2], 1);
-        assert_eq!(cycles[3], 4);
-        assert_eq!(cycles[4], 4);
-        assert_eq!(cycles[5], -1);
+        let program = input_generator(TEST_INPUT).unwrap();
+        let mut recorder = Recorder::default();
+        assert_eq!(Cpu::new(&program).run(&mut recorder), Stop::Halted);
+        assert_eq!(recorder.during, vec![1, 1, 1, 4, 4, -1]);
+        assert_eq!(recorder.after, vec![1, 1, 4, 4, -1, -1]);
+    }
+
+    #[test]
+    fn breakpoints() {
+        let program = input_generator(TEST_INPUT).unwrap();
+        let mut cpu = Cpu::new(&program);
+        cpu.add_breakpoint(3);
+        assert_eq!(cpu.run(&mut ()), Stop::Breakpoint(3));
+        assert_eq!(cpu.registers().x, 4);
+        assert_eq!(cpu.step(&mut ()), Some(4));
+        assert_eq!(cpu.run(&mut ()), Stop::Halted);
+        assert_eq!(cpu.cycle(), 6);
+        assert!(cpu.is_halted());
+        assert_eq!(cpu.step(&mut ()), None);
+    }
+
+    #[test]
+    fn instruction_table() {
+        let program = input_generator(TEST_INPUT).unwrap();
+        let mut cpu = Cpu::with_table(&program, InstructionTable::default().with_cost("addx", 3));
+        assert_eq!(cpu.run(&mut ()), Stop::Halted);
+        assert_eq!(cpu.cycle(), 8);
+    }
+
+    #[test]
+    fn shared_emulation() {
+        let program = input_generator(TEST_INPUT2).unwrap();
+        let mut observers = (SignalStrength::default(), Crt::default());
+        Cpu::new(&program).run(&mut observers);
+        assert_eq!(observers.0.total, 13140);
+        assert_eq!(print_crt(&observers.1.screen), solve_part2(&program));
+    }
+
+    #[test]
+    fn export() {
+        let program = input_generator(TEST_INPUT2).unwrap();
+        let crt = draw(&program, &CrtConfig::default());
+        let bytes = crt.to_pbm(2);
+        assert!(bytes.starts_with(b"P4\n80 12\n"));
+        assert_eq!(bytes.len(), "P4\n80 12\n".len() + 10 * 12);
+        assert_eq!(bytes["P4\n80 12\n".len()], 0b1111_0000);
+
+        let mut animation = Animation::default();
+        Cpu::new(&program).run(&mut animation);
+        assert_eq!(animation.frames.len(), 240);
+        assert_eq!(animation.frames[0][Position::new(0, 0)], BEAM);
+        assert_eq!(animation.frames[1][Position::new(0, 0)], 0);
+        assert_eq!(animation.crt.bitmap(), crt.bitmap());
+        assert_eq!(animation.to_pgm(1).len(), 240 * ("P5\n40 6\n255\n".len() + 240));
+    }
+
+    #[test]
+    fn config() {
+        let program = input_generator(TEST_INPUT2).unwrap();
+        let config = CrtConfig {
+            sprite_width: 1,
+            sample_start: 10,
+            sample_interval: 100,
+            sample_count: 3,
+            ..CrtConfig::new(20, 12).unwrap()
+        };
+        let crt = draw(&program, &config);
+        assert_eq!((crt.screen.len(), crt.screen[0].len()), (12, 20));
+        let mut recorder = Recorder::default();
+        Cpu::new(&program).run(&mut recorder);
+        let expected = recorder.during.iter().enumerate()
+            .map(|(i, &x)| x == (i % 20) as isize)
+            .collect::<Vec<bool>>();
+        assert_eq!(crt.bitmap(), Grid::new(20, 12, expected));
+        let expected = [10, 110, 210].iter().map(|&c| c as isize * recorder.during[c - 1]).sum::<isize>();
+        assert_eq!(signal_strength(&program, &config), expected);
+        assert!(config.sprite_covers(5, 5) && !config.sprite_covers(5, 6));
+        assert!(CrtConfig { sprite_width: 4, ..config }.sprite_covers(5, 7));
+        let empty = draw(&program, &CrtConfig { height: 0, ..config.clone() });
+        assert_eq!((empty.bitmap().width(), empty.bitmap().height()), (20, 0));
+        assert_eq!(CrtConfig::new(0, 12), Err(CrtConfigError::ZeroWidth));
+        let hidden = draw(&program, &CrtConfig { sprite_width: 0, ..config.clone() });
+        assert!(hidden.bitmap().iter().all(|(_, &on)| !on));
+        let single = signal_strength(&program, &CrtConfig { sample_interval: 0, ..config.clone() });
+        assert_eq!(single, 10 * recorder.during[9]);
+    }
+
+    #[test]
+    fn invalid_input() {
+        let e = input_generator("noop\naddx\nnoop").err().unwrap();
+        assert_eq!((e.line, e.column), (2, 5));
+        let e = input_generator("noop\nmulx 3").err().unwrap();
+        assert_eq!((e.line, e.column, e.text.as_str()), (2, 1, "mulx"));
+    }
+
+    #[test]
+    fn assembler() {
+        let source = "; draws the test pattern
+start:
+  addx   3   ;bump
+loop: noop
+
+addx -5";
+        let program = Program::assemble(source).unwrap();
+        assert_eq!(program.instructions().cloned().collect::<Vec<Instruction>>(),
+                   vec![Instruction::Addx(3), Instruction::Noop, Instruction::Addx(-5)]);
+        assert_eq!(program.labels(), HashMap::from([("start", 0), ("loop", 1)]));
+
+        let canonical = program.to_string();
+        assert_eq!(canonical, "; draws the test pattern
+start:
+addx 3 ; bump
+loop: noop
+
+addx -5");
+        assert_eq!(Program::assemble(&canonical).unwrap(), program);
+    }
+
+    #[test]
+    fn assembler_errors() {
+        let e = Program::assemble("noop\n  jmp loop").unwrap_err();
+        assert_eq!((e.line, e.column, e.text.as_str()), (2, 3, "jmp"));
+        let e = Program::assemble("a: noop\na: noop").unwrap_err();
+        assert_eq!((e.line, e.column), (2, 1));
+        let e = Program::assemble("noop 1").unwrap_err();
+        assert_eq!((e.line, e.column), (1, 6));
+        let e = Program::assemble("1a: noop").unwrap_err();
+        assert_eq!(e.text, "1a");
     }
 
     #[test]
     fn part1() {
-        assert_eq!(solve_part1(&input_generator(TEST_INPUT2)), 13140);
+        assert_eq!(solve_part1(&input_generator(TEST_INPUT2).unwrap()), 13140);
     }
 
     #[test]
     fn part2() {
-        assert_eq!(solve_part2(&input_generator(TEST_INPUT2)), "##..##..##..##..##..##..##..##..##..##..
+        assert_eq!(solve_part2(&input_generator(TEST_INPUT2).unwrap()), "##..##..##..##..##..##..##..##..##..##..
 ###...###...###...###...###...###...###.
 ####....####....####....####....####....
 #####.....#####.....#####.....#####.....
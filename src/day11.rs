@@ -1,120 +1,622 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 use aoc_runner_derive::{aoc, aoc_generator};
+use num_bigint::BigUint;
+use crate::parse::{get_line, parse, strip_prefix, ParseError};
 
-#[derive(Clone)]
-enum Operation {
-    Add(usize),
-    Mul(usize),
-    Square,
-}
-
-impl Operation {
-    fn apply_with_divisor(&self, x: usize, denominator: usize, divisor: usize) -> (usize, bool) {
-        let ret = match self {
-            Operation::Add(y) => (x + y) / divisor,
-            Operation::Mul(y) => (x * y) / divisor,
-            Operation::Square => x * x / divisor,
-        };
-        (ret, ret % denominator == 0)
+/// Worry level arithmetic, the checked operations return `None` on overflow, underflow or
+/// division by zero.
+pub trait Worry: Clone + Display {
+    fn from_usize(x: usize) -> Self;
+    fn checked_add(&self, y: &Self) -> Option<Self>;
+    fn checked_sub(&self, y: &Self) -> Option<Self>;
+    fn checked_mul(&self, y: &Self) -> Option<Self>;
+    fn checked_div(&self, y: &Self) -> Option<Self>;
+    fn div_usize(&self, y: usize) -> Self;
+    fn rem_usize(&self, y: usize) -> usize;
+}
+
+impl Worry for usize {
+    fn from_usize(x: usize) -> Self {
+        x
+    }
+
+    fn checked_add(&self, y: &Self) -> Option<Self> {
+        usize::checked_add(*self, *y)
+    }
+
+    fn checked_sub(&self, y: &Self) -> Option<Self> {
+        usize::checked_sub(*self, *y)
+    }
+
+    fn checked_mul(&self, y: &Self) -> Option<Self> {
+        usize::checked_mul(*self, *y)
+    }
+
+    fn checked_div(&self, y: &Self) -> Option<Self> {
+        usize::checked_div(*self, *y)
+    }
+
+    fn div_usize(&self, y: usize) -> Self {
+        self / y
+    }
+
+    fn rem_usize(&self, y: usize) -> usize {
+        self % y
+    }
+}
+
+/// Exact worry levels, which never overflow but grow without bounds without the modulo trick.
+impl Worry for BigUint {
+    fn from_usize(x: usize) -> Self {
+        BigUint::from(x)
+    }
+
+    fn checked_add(&self, y: &Self) -> Option<Self> {
+        Some(self + y)
+    }
+
+    fn checked_sub(&self, y: &Self) -> Option<Self> {
+        if self >= y {
+            Some(self - y)
+        } else {
+            None
+        }
+    }
+
+    fn checked_mul(&self, y: &Self) -> Option<Self> {
+        Some(self * y)
+    }
+
+    fn checked_div(&self, y: &Self) -> Option<Self> {
+        if *y == BigUint::ZERO {
+            None
+        } else {
+            Some(self / y)
+        }
+    }
+
+    fn div_usize(&self, y: usize) -> Self {
+        self / y
+    }
+
+    fn rem_usize(&self, y: usize) -> usize {
+        (self % y).iter_u64_digits().next().unwrap_or(0) as usize
+    }
+}
+
+/// Why an operation could not compute a new worry level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArithmeticError {
+    Overflow,
+    Underflow,
+    DivisionByZero,
+}
+
+impl Display for ArithmeticError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ArithmeticError::Overflow => "overflowed",
+            ArithmeticError::Underflow => "went below zero",
+            ArithmeticError::DivisionByZero => "was divided by zero",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeepAwayError {
+    /// `monkey` could not compute the new worry level of an item during `round`.
+    Inspection { round: usize, monkey: usize, worry_level: String, error: ArithmeticError },
+    /// The number of items inspected by `monkey` does not fit in a `usize`.
+    TooManyInspections { monkey: usize },
+    /// The level of monkey business does not fit in a `usize`.
+    MonkeyBusinessOverflow,
+}
+
+impl Display for KeepAwayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeepAwayError::Inspection { round, monkey, worry_level, error } =>
+                write!(f, "worry level {} {} when monkey {} inspected it during round {}", worry_level, error, monkey, round),
+            KeepAwayError::TooManyInspections { monkey } =>
+                write!(f, "monkey {} inspected more than {} items", monkey, usize::MAX),
+            KeepAwayError::MonkeyBusinessOverflow =>
+                write!(f, "the level of monkey business is over {}", usize::MAX),
+        }
+    }
+}
+
+impl Error for KeepAwayError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl BinOp {
+    fn symbol(&self) -> char {
+        match self {
+            BinOp::Add => '+',
+            BinOp::Sub => '-',
+            BinOp::Mul => '*',
+            BinOp::Div => '/',
+        }
     }
+}
+
+/// Right-hand side of a monkey operation, e.g. `(old + 2) * old`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expr {
+    Old,
+    Literal(usize),
+    Binary(Box<Expr>, BinOp, Box<Expr>),
+}
 
-    fn apply(&self, x: usize, denominator: usize, denominator_product: usize) -> (usize, bool) {
-        let x_ = x % denominator_product;
-        let ret = match self {
-            Operation::Add(y) => x_ + y,
-            Operation::Mul(y) => x_ * y,
-            Operation::Square => x_ * x_,
+impl Expr {
+    pub fn eval<W: Worry>(&self, old: &W) -> Result<W, ArithmeticError> {
+        match self {
+            Expr::Old => Ok(old.clone()),
+            Expr::Literal(y) => Ok(W::from_usize(*y)),
+            Expr::Binary(a, op, b) => {
+                let (a, b) = (a.eval(old)?, b.eval(old)?);
+                match op {
+                    BinOp::Add => a.checked_add(&b).ok_or(ArithmeticError::Overflow),
+                    BinOp::Sub => a.checked_sub(&b).ok_or(ArithmeticError::Underflow),
+                    BinOp::Mul => a.checked_mul(&b).ok_or(ArithmeticError::Overflow),
+                    BinOp::Div => a.checked_div(&b).ok_or(ArithmeticError::DivisionByZero),
+                }
+            }
+        }
+    }
+
+    /// Evaluates the expression modulo `modulus`, which requires `is_modular`.
+    ///
+    /// Intermediate results are computed on `u128`, so this never overflows whatever the modulus.
+    fn eval_mod(&self, old: usize, modulus: usize) -> usize {
+        match self {
+            Expr::Old => old % modulus,
+            Expr::Literal(y) => y % modulus,
+            Expr::Binary(a, op, b) => {
+                let (a, b) = (a.eval_mod(old, modulus) as u128, b.eval_mod(old, modulus) as u128);
+                let ret = match op {
+                    BinOp::Add => a + b,
+                    BinOp::Mul => a * b,
+                    BinOp::Sub | BinOp::Div => unreachable!("`{}` is not modular", op.symbol()),
+                };
+                (ret % modulus as u128) as usize
+            }
+        }
+    }
+
+    /// Whether the expression commutes with a modulo, i.e. only adds and multiplies.
+    ///
+    /// A subtraction would wrap around modulo instead of going below zero like the exact worry
+    /// levels, so it is not modular either.
+    pub fn is_modular(&self) -> bool {
+        match self {
+            Expr::Binary(a, op, b) => matches!(op, BinOp::Add | BinOp::Mul) && a.is_modular() && b.is_modular(),
+            _ => true,
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Old => write!(f, "old"),
+            Expr::Literal(y) => write!(f, "{}", y),
+            Expr::Binary(a, op, b) => write!(f, "({} {} {})", a, op.symbol(), b),
+        }
+    }
+}
+
+/// Recursive descent parser for `Expr`, `*` and `/` binding tighter than `+` and `-`.
+struct ExprParser<'a> {
+    idx: usize,
+    line: &'a str,
+    tokens: Vec<&'a str>,
+    pos: usize,
+}
+
+impl<'a> ExprParser<'a> {
+    fn new(idx: usize, line: &'a str, expr: &'a str) -> Result<Self, ParseError> {
+        let mut tokens = vec![];
+        let mut chars = expr.char_indices().peekable();
+        while let Some((j, c)) = chars.next() {
+            let mut end = j + c.len_utf8();
+            if c.is_ascii_alphanumeric() {
+                while let Some(&(k, c)) = chars.peek() {
+                    if !c.is_ascii_alphanumeric() {
+                        break;
+                    }
+                    end = k + 1;
+                    chars.next();
+                }
+            } else if c.is_whitespace() {
+                continue;
+            } else if !"+-*/()".contains(c) {
+                return Err(ParseError::new("an operator, a parenthesis, `old` or an integer", c).at_token(idx, line, &expr[j..end]));
+            }
+            tokens.push(&expr[j..end]);
+        }
+        Ok(ExprParser { idx, line, tokens, pos: 0 })
+    }
+
+    fn error(&self, expected: &str) -> ParseError {
+        match self.tokens.get(self.pos) {
+            Some(token) => ParseError::new(expected, *token).at_token(self.idx, self.line, token),
+            None => ParseError::new(expected, "end of line").at(self.idx, self.line.len()),
+        }
+    }
+
+    fn binary(&mut self, ops: &[BinOp], operand: fn(&mut Self) -> Result<Expr, ParseError>) -> Result<Expr, ParseError> {
+        let mut ret = operand(self)?;
+        while let Some(op) = self.tokens.get(self.pos)
+            .and_then(|t| ops.iter().find(|op| t.starts_with(op.symbol()))) {
+            self.pos += 1;
+            ret = Expr::Binary(Box::new(ret), *op, Box::new(operand(self)?));
+        }
+        Ok(ret)
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        self.binary(&[BinOp::Add, BinOp::Sub], Self::term)
+    }
+
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        self.binary(&[BinOp::Mul, BinOp::Div], Self::factor)
+    }
+
+    fn factor(&mut self) -> Result<Expr, ParseError> {
+        let expected = "`old`, an integer or `(`";
+        let token = *self.tokens.get(self.pos).ok_or_else(|| self.error(expected))?;
+        let ret = if token == "old" {
+            Expr::Old
+        } else if token == "(" {
+            self.pos += 1;
+            let ret = self.expr()?;
+            if self.tokens.get(self.pos) != Some(&")") {
+                return Err(self.error("`)`"));
+            }
+            ret
+        } else if token.starts_with(|c: char| c.is_ascii_digit()) {
+            Expr::Literal(parse(self.idx, self.line, token, "an integer")?)
+        } else {
+            return Err(self.error(expected));
         };
-        (ret, ret % denominator == 0)
+        self.pos += 1;
+        Ok(ret)
+    }
+
+    fn parse(mut self) -> Result<Expr, ParseError> {
+        let ret = self.expr()?;
+        if self.pos != self.tokens.len() {
+            return Err(self.error("an operator or end of line"));
+        }
+        Ok(ret)
     }
 }
 
 #[derive(Clone)]
 pub struct Monkey {
     items: Vec<usize>,
-    operation: Operation,
+    operation: Expr,
     denominator: usize,
     outcome: [usize; 2],
 }
 
 impl Monkey {
-    fn inspect_items(&self, items: Vec<usize>, worry_divisor: usize, denominator_product: usize) -> Vec<(usize, usize)> {
-        items.iter().map(|item| {
-            let (worry_level, outcome) = if worry_divisor != 1 {
-                self.operation.apply_with_divisor(*item, self.denominator, worry_divisor)
-            } else {
-                self.operation.apply(*item, self.denominator, denominator_product)
-            };
-            let monkey_id = self.outcome[outcome as usize];
-            (monkey_id, worry_level)
-        }).collect()
+    /// Returns the new worry level of `item` and the monkey it is thrown to. Worry levels are kept
+    /// modulo `modulus` if any, and relieved by `relief` otherwise.
+    fn inspect_item<W: Worry>(&self, item: &W, relief: &Relief<W>, modulus: Option<usize>) -> Result<(usize, W), ArithmeticError> {
+        let worry_level = match modulus {
+            Some(m) => W::from_usize(self.operation.eval_mod(item.rem_usize(m), m)),
+            None => relief.apply(self.operation.eval(item)?)?,
+        };
+        let outcome = worry_level.rem_usize(self.denominator) == 0;
+        Ok((self.outcome[outcome as usize], worry_level))
     }
 }
 
 
 #[aoc_generator(day11)]
-pub fn input_generator(input: &str) -> Vec<Monkey> {
-    input.lines().collect::<Vec<&str>>().chunks(7).map(|bloc| {
-        let split: Vec<&str> = bloc[1].split(": ").collect();
-        let items = split[1]
+pub fn input_generator(input: &str) -> Result<Vec<Monkey>, ParseError> {
+    let lines = input.lines().map(|l| l.trim_end()).collect::<Vec<&str>>();
+    let monkeys = (0..lines.len()).step_by(7).map(|first| {
+        let line = |j: usize, prefix: &str| -> Result<(usize, &str, &str), ParseError> {
+            let l = get_line(&lines, first + j, &format!("`{}`", prefix.trim_start()))?;
+            Ok((first + j, l, strip_prefix(first + j, l, prefix)?.trim_start()))
+        };
+
+        let (i, l, id) = line(0, "Monkey")?;
+        let id = id.strip_suffix(':').ok_or_else(|| ParseError::new("`:`", "end of line").at(i, l.len()))?;
+        if parse::<usize>(i, l, id, "a monkey number")? != first / 7 {
+            return Err(ParseError::new(format!("monkey {}", first / 7), id).at_token(i, l, id));
+        }
+
+        let (i, l, items) = line(1, "  Starting items:")?;
+        let items = items
             .split(", ")
-            .collect::<Vec<&str>>()
-            .iter()
-            .map(|i| i.parse().unwrap())
-            .collect::<Vec<usize>>();
+            .filter(|item| !item.is_empty())
+            .map(|item| parse(i, l, item, "a worry level"))
+            .collect::<Result<Vec<usize>, ParseError>>()?;
 
+        let (i, l, operation) = line(2, "  Operation: new =")?;
+        let operation = ExprParser::new(i, l, operation)?.parse()?;
 
-        let operation = if bloc[2].ends_with("old * old") {
-            Operation::Square
-        } else {
-            let split: Vec<&str> = bloc[2].split(" = ").collect();
-            let op: Vec<&str> = split[1].split(" ").collect();
-            if op[1] == "*" {
-                Operation::Mul(op[2].parse().unwrap())
-            } else {
-                Operation::Add(op[2].parse().unwrap())
-            }
-        };
+        let (i, l, denominator) = line(3, "  Test: divisible by")?;
+        let denominator = parse(i, l, denominator, "a divisor")?;
+        if denominator == 0 {
+            return Err(ParseError::new("a non-zero divisor", "0").at(i, l.len() - 1));
+        }
+        let (i, l, if_true) = line(4, "    If true: throw to monkey")?;
+        let if_true = (i, parse(i, l, if_true, "a monkey number")?);
+        let (i, l, if_false) = line(5, "    If false: throw to monkey")?;
+        let if_false = (i, parse(i, l, if_false, "a monkey number")?);
 
-        let denominator = bloc[3].split(" ").collect::<Vec<&str>>().pop().unwrap().parse().unwrap();
-        let if_true = bloc[4].split(" ").collect::<Vec<&str>>().pop().unwrap().parse().unwrap();
-        let if_false = bloc[5].split(" ").collect::<Vec<&str>>().pop().unwrap().parse().unwrap();
-        Monkey {
+        if let Some(l) = lines.get(first + 6) {
+            if !l.is_empty() {
+                return Err(ParseError::new("an empty line", *l).at(first + 6, 0));
+            }
+        }
+        Ok((Monkey {
             items,
             operation,
             denominator,
-            outcome: [if_false, if_true],
+            outcome: [if_false.1, if_true.1],
+        }, [if_false.0, if_true.0]))
+    }).collect::<Result<Vec<(Monkey, [usize; 2])>, ParseError>>()?;
+
+    for (monkey, outcome_lines) in &monkeys {
+        for (target, i) in monkey.outcome.iter().zip(outcome_lines) {
+            if *target >= monkeys.len() {
+                let l = lines[*i];
+                let token = l.rsplit(' ').next().unwrap();
+                return Err(ParseError::new(format!("a monkey number below {}", monkeys.len()), token).at_token(*i, l, token));
+            }
         }
-    }).collect()
+    }
+    Ok(monkeys.into_iter().map(|(m, _)| m).collect())
 }
 
-fn solve(monkeys: &[Monkey], round_count: usize, worry_divisor: usize) -> usize {
+/// How worry levels are relieved after each inspection.
+pub enum Relief<W> {
+    /// Divided by an integer, `Divide(3)` being the part 1 rule and `Divide(1)` no relief at all.
+    /// `Divide(0)` fails the first inspection with a division by zero.
+    Divide(usize),
+    /// Kept modulo the product of the monkeys' divisors, which leaves every test outcome unchanged.
+    ///
+    /// This requires every operation to be `is_modular` and the product to fit in a `usize`, worry
+    /// levels are kept exact otherwise.
+    Modulo,
+    Custom(Box<dyn Fn(W) -> W>),
+}
+
+impl<W: Worry> Relief<W> {
+    fn apply(&self, worry_level: W) -> Result<W, ArithmeticError> {
+        match self {
+            Relief::Divide(0) => Err(ArithmeticError::DivisionByZero),
+            Relief::Divide(d) => Ok(worry_level.div_usize(*d)),
+            Relief::Modulo => Ok(worry_level),
+            Relief::Custom(f) => Ok(f(worry_level)),
+        }
+    }
+
+    /// The modulus worry levels are kept under, if any.
+    fn modulus(&self, monkeys: &[Monkey]) -> Option<usize> {
+        match self {
+            Relief::Modulo if monkeys.iter().all(|m| m.operation.is_modular()) => {
+                monkeys.iter().try_fold(1usize, |product, m| product.checked_mul(m.denominator))
+            }
+            _ => None,
+        }
+    }
+}
+
+pub type ScoreFn = Box<dyn Fn(&[usize]) -> usize>;
+
+/// How the monkey business is computed from the number of items inspected by each monkey.
+pub enum Score {
+    /// Product of the `k` highest counts, the puzzle answer being `TopProduct(2)`.
+    TopProduct(usize),
+    Sum,
+    Max,
+    Custom(ScoreFn),
+}
+
+impl Score {
+    /// The monkey business, or `None` if it does not fit in a `usize`.
+    pub fn apply(&self, counts: &[usize]) -> Option<usize> {
+        match self {
+            Score::TopProduct(k) => {
+                let mut counts = counts.to_vec();
+                counts.sort_unstable_by(|a, b| b.cmp(a));
+                counts.iter().take(*k).try_fold(1usize, |product, &c| product.checked_mul(c))
+            }
+            Score::Sum => counts.iter().try_fold(0usize, |sum, &c| sum.checked_add(c)),
+            Score::Max => Some(counts.iter().copied().max().unwrap_or(0)),
+            Score::Custom(f) => Some(f(counts)),
+        }
+    }
+}
+
+/// A single item inspection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inspection<W> {
+    pub round: usize,
+    pub monkey: usize,
+    pub item: W,
+    pub worry_level: W,
+    pub target: usize,
+}
+
+/// Round by round state of the monkeys playing keep away.
+pub struct Simulation<'a, W> {
+    monkeys: &'a [Monkey],
+    relief: &'a Relief<W>,
+    modulus: Option<usize>,
+    round: usize,
+    items: Vec<Vec<W>>,
+    counts: Vec<usize>,
+}
+
+impl<'a, W: Worry> Simulation<'a, W> {
+    pub fn new(monkeys: &'a [Monkey], relief: &'a Relief<W>) -> Self {
+        Simulation {
+            monkeys,
+            relief,
+            modulus: relief.modulus(monkeys),
+            round: 0,
+            items: monkeys.iter()
+                .map(|m| m.items.iter().map(|&i| W::from_usize(i)).collect())
+                .collect(),
+            counts: vec![0; monkeys.len()],
+        }
+    }
+
+    /// Number of rounds played so far.
+    pub fn round(&self) -> usize {
+        self.round
+    }
+
+    /// Worry levels of the items held by each monkey.
+    pub fn items(&self) -> &[Vec<W>] {
+        &self.items
+    }
+
+    /// Number of items inspected by each monkey.
+    pub fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+
+    /// Plays a round, calling `trace` on every inspection.
+    pub fn play_round(&mut self, trace: &mut impl FnMut(&Inspection<W>)) -> Result<(), KeepAwayError> {
+        self.round += 1;
+        for (i, monkey) in self.monkeys.iter().enumerate() {
+            self.counts[i] += self.items[i].len();
+            for item in std::mem::take(&mut self.items[i]) {
+                let (target, worry_level) = monkey.inspect_item(&item, self.relief, self.modulus)
+                    .map_err(|error| KeepAwayError::Inspection { round: self.round, monkey: i, worry_level: item.to_string(), error })?;
+                let inspection = Inspection { round: self.round, monkey: i, item, worry_level, target };
+                trace(&inspection);
+                self.items[target].push(inspection.worry_level)
+            }
+        }
+        Ok(())
+    }
+
+    /// Items held by each monkey, like the part 1 walkthrough.
+    pub fn holdings_report(&self) -> String {
+        let mut ret = format!("After round {}, the monkeys are holding items with these worry levels:", self.round);
+        for (i, items) in self.items.iter().enumerate() {
+            let items = items.iter().map(|w| w.to_string()).collect::<Vec<String>>();
+            ret += &format!("\nMonkey {}: {}", i, items.join(", "));
+        }
+        ret
+    }
+
+    /// Inspection counts of each monkey, like the part 2 walkthrough.
+    pub fn inspections_report(&self) -> String {
+        let mut ret = format!("== After round {} ==", self.round);
+        for (i, count) in self.counts.iter().enumerate() {
+            ret += &format!("\nMonkey {} inspected items {} times.", i, count);
+        }
+        ret
+    }
+}
+
+/// Inspection counts after `round_count` rounds, following each item on its own.
+///
+/// Worry levels kept modulo `modulus` never interact, so every item goes through a finite set of
+/// `(monkey, worry level)` states at the start of a round. Once a state repeats, the inspections
+/// of the remaining rounds are extrapolated from that cycle.
+fn fast_forward(monkeys: &[Monkey], round_count: usize, modulus: usize) -> Result<Vec<usize>, KeepAwayError> {
     let mut counts = vec![0; monkeys.len()];
-    let denominator_product = monkeys.iter().map(|m| m.denominator).product::<usize>();
-    let mut items = monkeys.iter().map(|m| m.items.clone()).collect::<Vec<Vec<usize>>>();
-    for _ in 0..round_count {
-        for (i, monkey) in monkeys.iter().enumerate() {
-            let ret = monkey.inspect_items(items[i].clone(), worry_divisor, denominator_product);
-            counts[i] += items[i].len();
-            items[i] = vec![];
-            ret.iter().for_each(|(monkey_id, item)| {
-                items[*monkey_id].push(*item)
-            })
+    for (start, monkey) in monkeys.iter().enumerate() {
+        for &item in &monkey.items {
+            let mut state = (start, item % modulus);
+            // history[r] holds the inspections of this item during its first r rounds
+            let mut history = vec![vec![0; monkeys.len()]];
+            let mut seen = HashMap::from([(state, 0)]);
+            let mut rounds = round_count;
+            while history.len() <= round_count {
+                let round = history.len();
+                let mut current = history[round - 1].clone();
+                let (mut i, mut worry_level) = state;
+                loop {
+                    current[i] += 1;
+                    let (target, w) = monkeys[i].inspect_item(&worry_level, &Relief::Modulo, Some(modulus))
+                        .map_err(|error| KeepAwayError::Inspection { round, monkey: i, worry_level: worry_level.to_string(), error })?;
+                    worry_level = w;
+                    // Monkeys play in order, an item thrown backwards waits for the next round
+                    if target <= i {
+                        i = target;
+                        break;
+                    }
+                    i = target;
+                }
+                state = (i, worry_level);
+                history.push(current);
+                if let Some(&first) = seen.get(&state) {
+                    let period = round - first;
+                    let cycles = (round_count - first) / period;
+                    rounds = first + (round_count - first) % period;
+                    for (monkey, (c, (end, start))) in counts.iter_mut().zip(history[round].iter().zip(&history[first])).enumerate() {
+                        *c = cycles.checked_mul(end - start)
+                            .and_then(|n| usize::checked_add(*c, n))
+                            .ok_or(KeepAwayError::TooManyInspections { monkey })?;
+                    }
+                    break;
+                }
+                seen.insert(state, round);
+            }
+            for (monkey, (c, n)) in counts.iter_mut().zip(&history[rounds]).enumerate() {
+                *c = usize::checked_add(*c, *n).ok_or(KeepAwayError::TooManyInspections { monkey })?;
+            }
         }
     }
-    counts.sort();
-    counts.reverse();
-    println!("{:?}", counts);
-    counts[0] * counts[1]
+    Ok(counts)
+}
+
+/// Number of items inspected by each monkey after `round_count` rounds.
+///
+/// With `Relief::Modulo` the rounds are fast-forwarded, so round counts in the billions are fine,
+/// and worry levels never overflow as long as the divisors product fits in a `usize`.
+pub fn simulate<W: Worry>(monkeys: &[Monkey], round_count: usize, relief: &Relief<W>) -> Result<Vec<usize>, KeepAwayError> {
+    if let Some(m) = relief.modulus(monkeys) {
+        return fast_forward(monkeys, round_count, m);
+    }
+    let mut simulation = Simulation::new(monkeys, relief);
+    for _ in 0..round_count {
+        simulation.play_round(&mut |_| ())?;
+    }
+    Ok(simulation.counts)
+}
+
+/// Same as `simulate` but with exact worry levels, which never overflow but may still go below
+/// zero or be divided by zero. This is the way to go with `Relief::Modulo` when the divisors
+/// product does not fit in a `usize`.
+///
+/// Without the modulo trick, `old * old` doubles the size of a worry level every time it is
+/// applied, so runs with such monkeys still get slower as rounds go.
+pub fn simulate_exact(monkeys: &[Monkey], round_count: usize, relief: &Relief<BigUint>) -> Result<Vec<usize>, KeepAwayError> {
+    simulate(monkeys, round_count, relief)
 }
 
 #[aoc(day11, part1)]
-pub fn solve_part1(input: &[Monkey]) -> usize {
-    solve(input, 20, 3)
+pub fn solve_part1(input: &[Monkey]) -> Result<usize, KeepAwayError> {
+    Score::TopProduct(2).apply(&simulate::<usize>(input, 20, &Relief::Divide(3))?)
+        .ok_or(KeepAwayError::MonkeyBusinessOverflow)
 }
 
 #[aoc(day11, part2)]
-pub fn solve_part2(input: &[Monkey]) -> usize {
-    solve(input, 10000, 1)
+pub fn solve_part2(input: &[Monkey]) -> Result<usize, KeepAwayError> {
+    Score::TopProduct(2).apply(&simulate::<usize>(input, 10000, &Relief::Modulo)?)
+        .ok_or(KeepAwayError::MonkeyBusinessOverflow)
 }
 
 #[cfg(test)]
@@ -150,13 +652,235 @@ Monkey 3:
     If false: throw to monkey 1
 ";
 
+    #[test]
+    fn invalid_input() {
+        let e = input_generator(&TEST_INPUT.replace("old + 6", "old % 6")).err().unwrap();
+        assert_eq!((e.line, e.column, e.text.as_str()), (10, 24, "%"));
+        let e = input_generator(&TEST_INPUT.replace("monkey 3", "monkey 4")).err().unwrap();
+        assert_eq!((e.line, e.column, e.text.as_str()), (6, 31, "4"));
+    }
+
+    fn expr(s: &str) -> Result<Expr, ParseError> {
+        ExprParser::new(0, s, s)?.parse()
+    }
+
+    #[test]
+    fn expressions() {
+        assert_eq!(expr("old * old").unwrap().eval(&7usize), Ok(49));
+        assert_eq!(expr("old + old").unwrap().eval(&7usize), Ok(14));
+        assert_eq!(expr("old + 2 * 3").unwrap().eval(&1usize), Ok(7));
+        assert_eq!(expr("(old + 2) * 3").unwrap().eval(&1usize), Ok(9));
+        assert_eq!(expr("old - 10 - 2").unwrap().eval(&20usize), Ok(8));
+        assert_eq!(expr("old / 2 / 2").unwrap().eval(&20usize), Ok(5));
+        assert_eq!(expr("old - 10").unwrap().eval(&5usize), Err(ArithmeticError::Underflow));
+        assert_eq!(expr("old / (old - old)").unwrap().eval(&5usize), Err(ArithmeticError::DivisionByZero));
+        assert_eq!(expr("old * old").unwrap().eval(&usize::MAX), Err(ArithmeticError::Overflow));
+        assert_eq!(expr("(old+1)*old").unwrap().to_string(), "((old + 1) * old)");
+        assert_eq!(expr("old * (old + 3)").unwrap().eval_mod(5, 13), 1);
+        assert_eq!(expr("old * old + old").unwrap().eval_mod(usize::MAX - 1, usize::MAX), 0);
+
+        assert!(expr("old * (old + 3)").unwrap().is_modular());
+        assert!(!expr("old * (old - 3)").unwrap().is_modular());
+        assert!(!expr("old * (old / 3)").unwrap().is_modular());
+    }
+
+    #[test]
+    fn expression_errors() {
+        let e = expr("old +").unwrap_err();
+        assert_eq!((e.column, e.text.as_str()), (6, "end of line"));
+        let e = expr("(old + 1").unwrap_err();
+        assert_eq!(e.expected, "`)`");
+        let e = expr("old 3").unwrap_err();
+        assert_eq!((e.column, e.text.as_str()), (5, "3"));
+        let e = expr("new * 2").unwrap_err();
+        assert_eq!((e.column, e.text.as_str()), (1, "new"));
+    }
+
+    #[test]
+    fn division() {
+        let monkeys = input_generator(&TEST_INPUT.replace("old + 3", "(old + 3) / 2")).unwrap();
+        // No modulo trick with a division, worry levels are kept exact
+        assert_eq!(simulate::<usize>(&monkeys, 5, &Relief::Modulo), simulate_exact(&monkeys, 5, &Relief::Modulo));
+        assert!(simulate::<usize>(&monkeys, 20, &Relief::Modulo).is_err());
+        assert_eq!(Score::TopProduct(2).apply(&simulate_exact(&monkeys, 20, &Relief::Modulo).unwrap()), Some(10500));
+    }
+
+    #[test]
+    fn subtraction() {
+        let monkeys = input_generator(&TEST_INPUT.replace("98\n", "5\n").replace("old * 19", "old - 7")).unwrap();
+        // Worry levels would wrap around modulo the divisors product, so they are kept exact
+        assert_eq!(Relief::<usize>::Modulo.modulus(&monkeys), None);
+        let underflow = KeepAwayError::Inspection { round: 1, monkey: 0, worry_level: "5".into(), error: ArithmeticError::Underflow };
+        assert_eq!(simulate::<usize>(&monkeys, 1, &Relief::Modulo), Err(underflow.clone()));
+        assert_eq!(simulate::<usize>(&monkeys, 1, &Relief::Divide(1)), Err(underflow.clone()));
+        assert_eq!(simulate_exact(&monkeys, 1, &Relief::Modulo), Err(underflow.clone()));
+        assert_eq!(underflow.to_string(), "worry level 5 went below zero when monkey 0 inspected it during round 1");
+        let monkeys = input_generator(&TEST_INPUT.replace("old * 19", "old / (old - 79)")).unwrap();
+        let e = simulate_exact(&monkeys, 1, &Relief::Modulo).unwrap_err();
+        assert!(matches!(e, KeepAwayError::Inspection { error: ArithmeticError::DivisionByZero, .. }));
+    }
+
+    #[test]
+    fn large_divisors() {
+        let input = ["23", "19", "13", "17"].iter()
+            .fold(TEST_INPUT.to_owned(), |input, d| input.replace(&format!("by {}\n", d), &format!("by {}{}\n", d, "0".repeat(5))));
+        let monkeys = input_generator(&input).unwrap();
+        // The divisors product does not fit in a usize, worry levels are kept exact
+        assert_eq!(Relief::<usize>::Modulo.modulus(&monkeys), None);
+        assert_eq!(simulate::<usize>(&monkeys, 20, &Relief::Modulo), simulate_exact(&monkeys, 20, &Relief::Modulo));
+    }
+
+    #[test]
+    fn wide_modulus() {
+        let input = ["23", "19", "13", "17"].iter()
+            .fold(TEST_INPUT.to_owned(), |input, d| input.replace(&format!("by {}\n", d), &format!("by {}00\n", d)));
+        let monkeys = input_generator(&input).unwrap();
+        // The divisors product fits in a usize but squaring a worry level below it does not
+        let modulus = Relief::<usize>::Modulo.modulus(&monkeys).unwrap();
+        assert!(modulus > 1 << 32);
+        let mut simulation = Simulation::<usize>::new(&monkeys, &Relief::Modulo);
+        for _ in 0..10000 {
+            simulation.play_round(&mut |_| ()).unwrap();
+        }
+        assert_eq!(simulate::<usize>(&monkeys, 10000, &Relief::Modulo).unwrap(), simulation.counts());
+    }
+
+    #[test]
+    fn exact_beyond_usize() {
+        let input = ["23", "19", "13", "17"].iter()
+            .fold(TEST_INPUT.replace("old * 19", "old * old"), |input, d| input.replace(&format!("by {}\n", d), &format!("by {}{}\n", d, "0".repeat(5))));
+        let monkeys = input_generator(&input).unwrap();
+        // No modulus fits in a usize, so worry levels squared by two monkeys soon outgrow one
+        assert_eq!(simulate::<usize>(&monkeys, 6, &Relief::Modulo), simulate_exact(&monkeys, 6, &Relief::Modulo));
+        let Err(KeepAwayError::Inspection { round: 7, error: ArithmeticError::Overflow, .. }) = simulate::<usize>(&monkeys, 10, &Relief::Modulo) else {
+            panic!("usize worry levels should overflow");
+        };
+        assert_eq!(simulate_exact(&monkeys, 10, &Relief::Modulo), Ok(vec![46, 50, 3, 50]));
+    }
+
+    #[test]
+    fn trace() {
+        let monkeys = input_generator(TEST_INPUT).unwrap();
+        let mut simulation = Simulation::<usize>::new(&monkeys, &Relief::Divide(3));
+        let mut inspections = vec![];
+        simulation.play_round(&mut |i| inspections.push(i.clone())).unwrap();
+        assert_eq!(inspections.len(), 14);
+        assert_eq!(inspections[0], Inspection { round: 1, monkey: 0, item: 79, worry_level: 500, target: 3 });
+        assert_eq!(inspections[13], Inspection { round: 1, monkey: 3, item: 3136, worry_level: 1046, target: 1 });
+        assert_eq!(simulation.holdings_report(), "After round 1, the monkeys are holding items with these worry levels:
+Monkey 0: 20, 23, 27, 26
+Monkey 1: 2080, 25, 167, 207, 401, 1046
+Monkey 2: 
+Monkey 3: ");
+    }
+
+    #[test]
+    fn inspections_report() {
+        let monkeys = input_generator(TEST_INPUT).unwrap();
+        let mut simulation = Simulation::<usize>::new(&monkeys, &Relief::Modulo);
+        for _ in 0..20 {
+            simulation.play_round(&mut |_| ()).unwrap();
+        }
+        assert_eq!(simulation.inspections_report(), "== After round 20 ==
+Monkey 0 inspected items 99 times.
+Monkey 1 inspected items 97 times.
+Monkey 2 inspected items 8 times.
+Monkey 3 inspected items 103 times.");
+    }
+
     #[test]
     fn part1() {
-        assert_eq!(solve_part1(&input_generator(TEST_INPUT)), 10605);
+        assert_eq!(solve_part1(&input_generator(TEST_INPUT).unwrap()), Ok(10605));
     }
 
     #[test]
     fn part2() {
-        assert_eq!(solve_part2(&input_generator(TEST_INPUT)), 2713310158);
+        assert_eq!(solve_part2(&input_generator(TEST_INPUT).unwrap()), Ok(2713310158));
+    }
+
+    #[test]
+    fn fast_forward_matches_simulation() {
+        let monkeys = input_generator(TEST_INPUT).unwrap();
+        let mut simulation = Simulation::<usize>::new(&monkeys, &Relief::Modulo);
+        for rounds in 1..=2000 {
+            simulation.play_round(&mut |_| ()).unwrap();
+            if rounds % 97 == 0 || rounds < 30 {
+                assert_eq!(fast_forward(&monkeys, rounds, 96577).unwrap(), simulation.counts());
+            }
+        }
+    }
+
+    #[test]
+    fn billions_of_rounds() {
+        let monkeys = input_generator(TEST_INPUT).unwrap();
+        // Every item is back to an earlier state every 171 or 448 rounds once past round 175, so
+        // the counts grow by the same amount every 171 * 448 rounds
+        let (start, period) = (1000, 171 * 448);
+        let mut simulation = Simulation::<usize>::new(&monkeys, &Relief::Modulo);
+        let mut measured = vec![];
+        for round in 1..=start + 2 * period {
+            simulation.play_round(&mut |_| ()).unwrap();
+            if round >= start && (round - start) % period == 0 {
+                measured.push(simulation.counts().to_vec());
+            }
+        }
+        let growth = |from: &[usize], to: &[usize]| to.iter().zip(from).map(|(t, f)| t - f).collect::<Vec<usize>>();
+        assert_eq!(growth(&measured[0], &measured[1]), growth(&measured[1], &measured[2]));
+
+        let cycles = 65_000;
+        let expected = measured[0].iter().zip(growth(&measured[0], &measured[1]))
+            .map(|(c, g)| c + cycles * g)
+            .collect::<Vec<usize>>();
+        let counts = simulate::<usize>(&monkeys, start + cycles * period, &Relief::Modulo).unwrap();
+        assert_eq!(counts, expected);
+        assert_eq!(Score::TopProduct(2).apply(&counts), None);
+    }
+
+    #[test]
+    fn overflow() {
+        let monkeys = input_generator(TEST_INPUT).unwrap();
+        let Err(KeepAwayError::Inspection { round, monkey, worry_level, error }) = simulate::<usize>(&monkeys, 100, &Relief::Divide(3)) else {
+            panic!("expected an overflow")
+        };
+        assert_eq!((round, monkey, error), (92, 2, ArithmeticError::Overflow));
+        assert!(worry_level.parse::<usize>().unwrap() > u32::MAX as usize);
+        assert_eq!(simulate::<usize>(&monkeys, usize::MAX, &Relief::Modulo), Err(KeepAwayError::TooManyInspections { monkey: 0 }));
+    }
+
+    #[test]
+    fn exact() {
+        let monkeys = input_generator(TEST_INPUT).unwrap();
+        assert_eq!(simulate_exact(&monkeys, 20, &Relief::Divide(3)), Ok(vec![101, 95, 7, 105]));
+        assert_eq!(Score::TopProduct(2).apply(&simulate_exact(&monkeys, 1000, &Relief::Modulo).unwrap()), Some(27019168));
+        assert_eq!(simulate::<usize>(&monkeys, 50, &Relief::Divide(3)), simulate_exact(&monkeys, 50, &Relief::Divide(3)));
+        assert!(simulate::<usize>(&monkeys, 100, &Relief::Divide(3)).is_err());
+        assert!(simulate_exact(&monkeys, 100, &Relief::Divide(3)).unwrap()[0] > simulate_exact(&monkeys, 50, &Relief::Divide(3)).unwrap()[0]);
+    }
+
+    #[test]
+    fn relief() {
+        let monkeys = input_generator(TEST_INPUT).unwrap();
+        assert_eq!(simulate::<usize>(&monkeys, 10000, &Relief::Modulo), Ok(vec![52166, 47830, 1938, 52013]));
+        let halve = Relief::Custom(Box::new(|w: usize| w / 2));
+        assert_eq!(simulate(&monkeys, 20, &halve), simulate::<usize>(&monkeys, 20, &Relief::Divide(2)));
+        assert_eq!(simulate_exact(&monkeys, 20, &Relief::Custom(Box::new(|w| w / 3u32))), Ok(vec![101, 95, 7, 105]));
+        let division_by_zero = KeepAwayError::Inspection { round: 1, monkey: 0, worry_level: "79".into(), error: ArithmeticError::DivisionByZero };
+        assert_eq!(simulate::<usize>(&monkeys, 20, &Relief::Divide(0)), Err(division_by_zero.clone()));
+        assert_eq!(simulate_exact(&monkeys, 20, &Relief::Divide(0)), Err(division_by_zero));
+    }
+
+    #[test]
+    fn scores() {
+        let counts = [101, 95, 7, 105];
+        assert_eq!(Score::TopProduct(2).apply(&counts), Some(10605));
+        assert_eq!(Score::TopProduct(3).apply(&counts), Some(10605 * 95));
+        assert_eq!(Score::TopProduct(9).apply(&counts), Some(10605 * 95 * 7));
+        assert_eq!(Score::Sum.apply(&counts), Some(308));
+        assert_eq!(Score::Max.apply(&counts), Some(105));
+        assert_eq!(Score::Custom(Box::new(|c| c.iter().min().copied().unwrap_or(0))).apply(&counts), Some(7));
+        let counts = [1 << 20, 1 << 40, 1 << 30];
+        assert_eq!(Score::TopProduct(1).apply(&counts), Some(1 << 40));
+        assert_eq!(Score::TopProduct(2).apply(&counts), None);
+        assert_eq!(Score::Sum.apply(&[usize::MAX, 1]), None);
     }
 }
@@ -1,116 +1,137 @@
+use std::collections::VecDeque;
 use aoc_runner_derive::{aoc, aoc_generator};
-
-#[derive(Default, Debug, PartialEq)]
-struct Position {
-    x: usize,
-    y: usize,
-}
+use crate::grid::{Direction, Grid, Position};
+use crate::parse::ParseError;
 
 #[derive(Default, Debug)]
 pub struct HeightMap {
     start: Position,
     end: Position,
-    heights: Vec<Vec<u8>>,
+    heights: Grid<u8>,
 }
 
-impl HeightMap {
-    fn get_height(&self, pos: &Position) -> u8 {
-        self.heights[pos.x][pos.y]
+/// Shortest routes from every cell to `end`, as found by `HeightMap::routes`.
+pub struct Routes {
+    end: Position,
+    steps: Grid<Option<usize>>,
+    next: Grid<Option<Position>>,
+}
+
+impl Routes {
+    /// Number of steps needed to reach `end` from `from`, `None` if it is unreachable.
+    pub fn steps(&self, from: Position) -> Option<usize> {
+        self.steps.get(from).copied().flatten()
     }
 
-    fn find_candidates(&self, current: &Position) -> Vec<Position> {
-        let mut ret = vec![];
-        let dim_x = self.heights.len() - 1;
-        let dim_y = self.heights[0].len() - 1;
-        let cur_height = self.get_height(current);
-
-        if 0 < current.x {
-            let candidate = Position { x: current.x - 1, y: current.y };
-            let c_height = self.get_height(&candidate);
-            if c_height <= cur_height + 1 {
-                ret.push(candidate)
-            }
-        }
-        if current.x < dim_x {
-            let candidate = Position { x: current.x + 1, y: current.y };
-            let c_height = self.get_height(&candidate);
-            if c_height <= cur_height + 1 {
-                ret.push(candidate)
-            }
-        }
-        if 0 < current.y {
-            let candidate = Position { x: current.x, y: current.y - 1 };
-            let c_height = self.get_height(&candidate);
-            if c_height <= cur_height + 1 {
-                ret.push(candidate)
-            }
-        }
-        if current.y < dim_y {
-            let candidate = Position { x: current.x, y: current.y + 1 };
-            let c_height = self.get_height(&candidate);
-            if c_height <= cur_height + 1 {
-                ret.push(candidate)
-            }
+    /// The cells visited going from `from` to `end`, both included.
+    pub fn path(&self, from: Position) -> Option<Vec<Position>> {
+        self.steps(from)?;
+        let mut path = vec![from];
+        let mut current = from;
+        while current != self.end {
+            current = self.next[current]?;
+            path.push(current)
         }
-        ret
+        Some(path)
     }
 }
 
-#[aoc_generator(day12)]
-pub fn input_generator(input: &str) -> HeightMap {
-    let mut height_map = HeightMap::default();
-    input.lines().enumerate().for_each(|(x, l)| {
-        height_map.heights.push(l.trim()
-            .chars()
-            .enumerate()
-            .map(|(y, c)| {
-                match c {
-                    'S' => {
-                        height_map.start = Position { x, y };
-                        0
-                    }
-                    'E' => {
-                        height_map.end = Position { x, y };
-                        25
-                    }
-                    _ => c as u8 - 'a' as u8
-                }
-            }).collect())
-    });
-    height_map
-}
+impl HeightMap {
+    fn can_climb(&self, from: Position, to: Position) -> bool {
+        self.heights[to] <= self.heights[from] + 1
+    }
+
+    /// Cells from which `current` can be reached in a single step.
+    fn find_predecessors(&self, current: Position) -> impl Iterator<Item=Position> + '_ {
+        self.heights.neighbours4(current)
+            .filter(move |&c| self.can_climb(c, current))
+    }
 
-fn a_star(height_map: &HeightMap, current: &Position, end: &Position, cost: usize, acc: &mut [Vec<usize>]) -> usize {
-    if cost >= acc[current.x][current.y] {
-        return usize::MAX;
+    /// Breadth-first search from `end` following the climbing rules backwards.
+    pub fn routes(&self) -> Routes {
+        let mut routes = Routes {
+            end: self.end,
+            steps: self.heights.map(|_| None),
+            next: self.heights.map(|_| None),
+        };
+        let mut queue = VecDeque::from([self.end]);
+        routes.steps[self.end] = Some(0);
+        while let Some(current) = queue.pop_front() {
+            let cost = routes.steps[current].unwrap() + 1;
+            for c in self.find_predecessors(current) {
+                if routes.steps[c].is_none() {
+                    routes.steps[c] = Some(cost);
+                    routes.next[c] = Some(current);
+                    queue.push_back(c)
+                }
+            }
+        }
+        routes
     }
 
-    acc[current.x][current.y] = cost;
+    /// The shortest path from `start` to `end`, both included.
+    pub fn shortest_path(&self) -> Option<Vec<Position>> {
+        self.routes().path(self.start)
+    }
 
-    if *current == *end {
-        return cost;
+    /// Draws `path` like the puzzle statement: every cell of the path but the last shows an
+    /// arrow towards the next one, the last shows `E` and cells off the path show `.`.
+    pub fn render_path(&self, path: &[Position]) -> String {
+        let mut map = self.heights.map(|_| '.');
+        path.windows(2).for_each(|w| {
+            map[w[0]] = match w[0].direction_to(w[1]) {
+                Some(Direction::Up) => '^',
+                Some(Direction::Down) => 'v',
+                Some(Direction::Left) => '<',
+                Some(Direction::Right) => '>',
+                _ => '?',
+            }
+        });
+        if let Some(&last) = path.last() {
+            map[last] = 'E';
+        }
+        map.to_string()
     }
+}
 
-    height_map.find_candidates(current).iter().map(|c| {
-        a_star(height_map, &c, end, cost + 1, acc)
-    }).min().unwrap_or(usize::MAX)
+#[aoc_generator(day12)]
+pub fn input_generator(input: &str) -> Result<HeightMap, ParseError> {
+    let (mut start, mut end) = (None, None);
+    let heights = Grid::parse(input, |pos, c| {
+        match c {
+            'S' => {
+                start = Some(pos);
+                Ok(0)
+            }
+            'E' => {
+                end = Some(pos);
+                Ok(25)
+            }
+            'a'..='z' => Ok(c as u8 - b'a'),
+            _ => Err(ParseError::new("a height (a-z), `S` or `E`", c))
+        }
+    })?;
+    let rows = heights.height();
+    Ok(HeightMap {
+        start: start.ok_or_else(|| ParseError::new("a start marker `S`", "end of input").at(rows, 0))?,
+        end: end.ok_or_else(|| ParseError::new("an end marker `E`", "end of input").at(rows, 0))?,
+        heights,
+    })
 }
 
 #[aoc(day12, part1)]
-pub fn solve_part1(input: &HeightMap) -> usize {
-    let mut acc = vec![vec![usize::MAX; input.heights[0].len()]; input.heights.len()];
-    a_star(input, &input.start, &input.end, 0, &mut acc)
+pub fn solve_part1(input: &HeightMap) -> Option<usize> {
+    input.routes().steps(input.start)
 }
 
 
 #[aoc(day12, part2)]
-pub fn solve_part2(input: &HeightMap) -> usize {
-    input.heights.iter().enumerate().map(|(x, line)| {
-        let mut acc = vec![vec![usize::MAX; input.heights[0].len()]; input.heights.len()];
-        line.iter().enumerate()
-            .filter(|(_, h)| **h == 0)
-            .map(|(y, _)| a_star(input, &Position{x, y}, &input.end, 0, &mut acc)).min().unwrap()
-    }).min().unwrap()
+pub fn solve_part2(input: &HeightMap) -> Option<usize> {
+    let routes = input.routes();
+    input.heights.iter()
+        .filter(|(_, h)| **h == 0)
+        .filter_map(|(pos, _)| routes.steps(pos))
+        .min()
 }
 
 #[cfg(test)]
@@ -125,11 +146,43 @@ abdefghi";
 
     #[test]
     fn part1() {
-        assert_eq!(solve_part1(&input_generator(TEST_INPUT)), 31);
+        assert_eq!(solve_part1(&input_generator(TEST_INPUT).unwrap()), Some(31));
+    }
+
+    #[test]
+    fn path() {
+        let height_map = input_generator(TEST_INPUT).unwrap();
+        let path = height_map.shortest_path().unwrap();
+        assert_eq!(path.len(), 32);
+        assert_eq!(path[0], height_map.start);
+        assert_eq!(path[31], height_map.end);
+        assert!(path.windows(2).all(|w| height_map.heights.neighbours4(w[0]).any(|n| n == w[1]) && height_map.can_climb(w[0], w[1])));
+    }
+
+    #[test]
+    fn render() {
+        let height_map = input_generator(TEST_INPUT).unwrap();
+        let path = [Position::new(0, 0), Position::new(1, 0), Position::new(1, 1), Position::new(0, 1), Position::new(0, 2)];
+        assert_eq!(height_map.render_path(&path), "v>E.....
+>^......
+........
+........
+........");
+        let rendered = height_map.render_path(&height_map.shortest_path().unwrap());
+        assert_eq!(rendered.chars().filter(|c| "<>^v".contains(*c)).count(), 31);
+        assert_eq!(rendered.lines().nth(2).unwrap().chars().nth(5), Some('E'));
+    }
+
+    #[test]
+    fn unreachable() {
+        let height_map = input_generator("SbcE").unwrap();
+        assert_eq!(solve_part1(&height_map), None);
+        assert_eq!(solve_part2(&height_map), None);
+        assert_eq!(height_map.shortest_path(), None);
     }
 
     #[test]
     fn part2() {
-        assert_eq!(solve_part2(&input_generator(TEST_INPUT)), 29);
+        assert_eq!(solve_part2(&input_generator(TEST_INPUT).unwrap()), Some(29));
     }
 }
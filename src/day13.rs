@@ -7,6 +7,7 @@ use nom::character::complete::digit1;
 use nom::{Finish, IResult};
 use nom::multi::separated_list0;
 use nom::sequence::delimited;
+use crate::parse::{get_line, ParseError};
 
 #[derive(Clone, Eq, PartialEq)]
 pub enum Data {
@@ -54,7 +55,7 @@ impl Ord for Data {
 }
 
 fn parse_number(i: &str) -> IResult<&str, Data> {
-    map_res(digit1, |s: &str| s.parse::<u8>().map(|i| Data::Integer(i)))(i)
+    map_res(digit1, |s: &str| s.parse::<u8>().map(Data::Integer))(i)
 }
 
 fn parse_list(i: &str) -> IResult<&str, Data> {
@@ -65,17 +66,33 @@ fn parse_list(i: &str) -> IResult<&str, Data> {
             alt((
                 parse_number,
                 parse_list
-            ))), |s| Data::List(s)),
+            ))), Data::List),
         tag("]"),
     )(i)
 }
 
+fn parse_packet(idx: usize, line: &str) -> Result<Data, ParseError> {
+    all_consuming(parse_list)(line)
+        .finish()
+        .map(|(_, packet)| packet)
+        .map_err(|e| {
+            let found = e.input.chars().next().map_or("end of line".into(), |c| c.to_string());
+            ParseError::new("a packet", found).at_token(idx, line, e.input)
+        })
+}
+
 #[aoc_generator(day13)]
-pub fn input_generator(input: &str) -> Vec<[Data; 2]> {
-    input.lines().collect::<Vec<&str>>().chunks(3).map(|bloc| {
-        let (_, fst) = all_consuming(parse_list)(bloc[0]).finish().unwrap();
-        let (_, sec) = all_consuming(parse_list)(bloc[1]).finish().unwrap();
-        [fst, sec]
+pub fn input_generator(input: &str) -> Result<Vec<[Data; 2]>, ParseError> {
+    let lines = input.lines().map(|l| l.trim_end()).collect::<Vec<&str>>();
+    (0..lines.len()).step_by(3).map(|first| {
+        let fst = parse_packet(first, lines[first])?;
+        let sec = parse_packet(first + 1, get_line(&lines, first + 1, "a packet")?)?;
+        if let Some(l) = lines.get(first + 2) {
+            if !l.is_empty() {
+                return Err(ParseError::new("an empty line", *l).at(first + 2, 0));
+            }
+        }
+        Ok([fst, sec])
     }).collect()
 }
 
@@ -92,7 +109,7 @@ pub fn solve_part2(input: &[[Data; 2]]) -> usize {
     let dividers_str = "[[2]]
 [[6]]
 ";
-    let dividers = &input_generator(dividers_str)[0];
+    let dividers = &input_generator(dividers_str).unwrap()[0];
     let mut flattened = input.iter()
         .flatten()
         .collect::<Vec<&Data>>();
@@ -134,11 +151,11 @@ mod tests {
 
     #[test]
     fn part1() {
-        assert_eq!(solve_part1(&input_generator(TEST_INPUT)), 13);
+        assert_eq!(solve_part1(&input_generator(TEST_INPUT).unwrap()), 13);
     }
 
     #[test]
     fn part2() {
-        assert_eq!(solve_part2(&input_generator(TEST_INPUT)), 140);
+        assert_eq!(solve_part2(&input_generator(TEST_INPUT).unwrap()), 140);
     }
 }
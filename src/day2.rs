@@ -1,4 +1,5 @@
 use aoc_runner_derive::{aoc, aoc_generator};
+use crate::parse::ParseError;
 
 #[derive(Eq, PartialEq, Clone)]
 enum Hand {
@@ -50,13 +51,15 @@ impl Hand {
     }
 }
 
-impl From<char> for Hand {
-    fn from(c: char) -> Self {
+impl TryFrom<char> for Hand {
+    type Error = ParseError;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
         match c {
-            'A' | 'X' => Hand::Rock,
-            'B' | 'Y' => Hand::Paper,
-            'C' | 'Z' => Hand::Scissor,
-            _ => unreachable!()
+            'A' | 'X' => Ok(Hand::Rock),
+            'B' | 'Y' => Ok(Hand::Paper),
+            'C' | 'Z' => Ok(Hand::Scissor),
+            _ => Err(ParseError::new("a hand (A, B, C, X, Y or Z)", c))
         }
     }
 }
@@ -75,13 +78,15 @@ impl Outcome {
     }
 }
 
-impl From<char> for Outcome {
-    fn from(desired_outcome: char) -> Self {
+impl TryFrom<char> for Outcome {
+    type Error = ParseError;
+
+    fn try_from(desired_outcome: char) -> Result<Self, Self::Error> {
         match desired_outcome {
-            'X' => Outcome::Loose,
-            'Y' => Outcome::Draw,
-            'Z' => Outcome::Win,
-            _ => unreachable!()
+            'X' => Ok(Outcome::Loose),
+            'Y' => Ok(Outcome::Draw),
+            'Z' => Ok(Outcome::Win),
+            _ => Err(ParseError::new("an outcome (X, Y or Z)", desired_outcome))
         }
     }
 }
@@ -105,16 +110,23 @@ impl Game {
 }
 
 #[aoc_generator(day2)]
-pub fn input_generator(input: &str) -> Vec<Game> {
-    input.lines().map(|l| {
-        let elf = l.chars().nth(0).unwrap().into();
-        let c2 = l.chars().nth(2).unwrap();
-        let outcome = Outcome::from(c2);
-        Game {
+pub fn input_generator(input: &str) -> Result<Vec<Game>, ParseError> {
+    input.lines().enumerate().map(|(i, l)| {
+        let l = l.trim_end();
+        let chars = l.char_indices().collect::<Vec<(usize, char)>>();
+        if chars.len() != 3 || chars[1].1 != ' ' {
+            return Err(ParseError::new("`<elf> <me>`", l).at(i, 0));
+        }
+        let (o1, c1) = chars[0];
+        let (o2, c2) = chars[2];
+        let elf = Hand::try_from(c1).map_err(|e| e.at(i, o1))?;
+        let me = Hand::try_from(c2).map_err(|e| e.at(i, o2))?;
+        let outcome = Outcome::try_from(c2).map_err(|e| e.at(i, o2))?;
+        Ok(Game {
             elf,
-            me: c2.into(),
+            me,
             outcome
-        }
+        })
     }).collect()
 }
 
@@ -127,3 +139,30 @@ pub fn solve_part1(input: &[Game]) -> u32 {
 pub fn solve_part2(input: &[Game]) -> u32 {
     input.iter().map(|g| g.outcome.score() + g.needed_hand().score()).sum()
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::day2::*;
+
+    const TEST_INPUT: &str = "A Y
+B X
+C Z";
+
+    #[test]
+    fn part1() {
+        assert_eq!(solve_part1(&input_generator(TEST_INPUT).unwrap()), 15);
+    }
+
+    #[test]
+    fn part2() {
+        assert_eq!(solve_part2(&input_generator(TEST_INPUT).unwrap()), 12);
+    }
+
+    #[test]
+    fn invalid_input() {
+        let e = input_generator("A Y\nB W").err().unwrap();
+        assert_eq!((e.line, e.column), (2, 3));
+        assert_eq!(e.text, "W");
+        assert!(input_generator("A Y \r\nB X").is_ok());
+    }
+}
@@ -1,5 +1,6 @@
 use std::collections::HashSet;
 use aoc_runner_derive::{aoc, aoc_generator};
+use crate::parse::ParseError;
 
 #[derive(Eq, PartialEq, Clone, Hash, Debug)]
 struct Item {
@@ -54,8 +55,17 @@ impl From<&str> for Bag {
 }
 
 #[aoc_generator(day3)]
-pub fn input_generator(input: &str) -> Vec<Bag> {
-    input.lines().map(|l| l.trim().into()).collect::<Vec<Bag>>()
+pub fn input_generator(input: &str) -> Result<Vec<Bag>, ParseError> {
+    input.lines().enumerate().map(|(i, l)| {
+        let s = l.trim();
+        if let Some((j, c)) = s.char_indices().find(|(_, c)| !c.is_ascii_alphabetic()) {
+            return Err(ParseError::new("an item (a-z or A-Z)", c).at_token(i, l, &s[j..j + c.len_utf8()]));
+        }
+        if s.len() % 2 != 0 {
+            return Err(ParseError::new("an even number of items", s).at_token(i, l, s));
+        }
+        Ok(s.into())
+    }).collect()
 }
 
 #[aoc(day3, part1)]
@@ -69,12 +79,11 @@ pub fn solve_part2(input: &[Bag]) -> u32 {
         let mut set: HashSet<Item> = g[0].to_set();
         g.iter().skip(1).for_each(|b| {
             set = HashSet::from_iter(
-                set.intersection(&b.to_set())
-                    .map(|r| r.clone())
+                set.intersection(&b.to_set()).cloned()
             );
             //println!("{} {:?}", set.len(), set)
         });
-        set.iter().nth(0).unwrap().priority()
+        set.iter().next().unwrap().priority()
     }).sum()
 }
 
@@ -112,11 +121,11 @@ CrZsJsPPZsGzwwsLwLmpwMDw";
 
     #[test]
     fn part1() {
-        assert_eq!(solve_part1(&input_generator(TEST_INPUT)), 157)
+        assert_eq!(solve_part1(&input_generator(TEST_INPUT).unwrap()), 157)
     }
 
     # [test]
     fn part2() {
-        assert_eq!(solve_part2(&input_generator(TEST_INPUT)), 70)
+        assert_eq!(solve_part2(&input_generator(TEST_INPUT).unwrap()), 70)
     }
 }
\ No newline at end of file
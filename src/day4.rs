@@ -1,4 +1,5 @@
 use aoc_runner_derive::{aoc, aoc_generator};
+use crate::parse::{parse, ParseError};
 
 #[derive(Clone, Copy)]
 struct Range {
@@ -30,20 +31,27 @@ impl Pair {
     }
 }
 
+fn parse_range(idx: usize, line: &str, r: &str) -> Result<Range, ParseError> {
+    let (start, end) = r.split_once('-')
+        .ok_or_else(|| ParseError::new("a range `<start>-<end>`", r).at_token(idx, line, r))?;
+    Ok(Range {
+        start: parse(idx, line, start, "a section id")?,
+        end: parse(idx, line, end, "a section id")?,
+    })
+}
+
 #[aoc_generator(day4)]
-pub fn input_generator(input: &str) -> Vec<Pair> {
+pub fn input_generator(input: &str) -> Result<Vec<Pair>, ParseError> {
     let mut ret = vec![];
-    for l in input.lines() {
+    for (i, l) in input.lines().enumerate() {
         let s = l.trim();
 
-        let ranges: Vec<Range> = s.split(",").map(|r| {
-            let bounds: Vec<&str> = r.split("-").collect();
-            Range { start: bounds[0].parse().unwrap(), end: bounds[1].parse().unwrap() }
-        }).collect();
+        let (r1, r2) = s.split_once(',')
+            .ok_or_else(|| ParseError::new("a pair of ranges `<range>,<range>`", s).at_token(i, l, s))?;
 
-        ret.push(Pair(ranges[0], ranges[1]));
+        ret.push(Pair(parse_range(i, l, r1)?, parse_range(i, l, r2)?));
     }
-    return ret;
+    Ok(ret)
 }
 
 #[aoc(day4, part1)]
@@ -72,7 +80,7 @@ mod tests {
     fn contains() {
         assert!(Range { start: 1, end: 3 }.contains(&Range { start: 1, end: 3 }));
         assert!(Range { start: 1, end: 3 }.contains(&Range { start: 1, end: 2 }));
-        assert_eq!(Range { start: 1, end: 2 }.contains(&Range { start: 1, end: 3 }), false);
+        assert!(!Range { start: 1, end: 2 }.contains(&Range { start: 1, end: 3 }));
     }
 
     #[test]
@@ -82,16 +90,16 @@ mod tests {
         assert!(Range { start: 1, end: 3 }.overlaps(&Range { start: 2, end: 4 }));
         assert!(Range { start: 1, end: 3 }.overlaps(&Range { start: 3, end: 4 }));
         assert!(Range { start: 1, end: 1 }.overlaps(&Range { start: 1, end: 1 }));
-        assert_eq!(Range { start: 1, end: 2 }.overlaps(&Range { start: 3, end: 4 }), false);
+        assert!(!Range { start: 1, end: 2 }.overlaps(&Range { start: 3, end: 4 }));
     }
 
     #[test]
     fn part1() {
-        assert_eq!(solve_part1(&input_generator(TEST_INPUT)), 2)
+        assert_eq!(solve_part1(&input_generator(TEST_INPUT).unwrap()), 2)
     }
 
     #[test]
     fn part2() {
-        assert_eq!(solve_part2(&input_generator(TEST_INPUT)), 4)
+        assert_eq!(solve_part2(&input_generator(TEST_INPUT).unwrap()), 4)
     }
 }
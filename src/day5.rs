@@ -1,4 +1,5 @@
 use aoc_runner_derive::{aoc, aoc_generator};
+use crate::parse::{expect_token, next_token, parse, ParseError};
 
 #[repr(transparent)]
 #[derive(Debug, Clone, Copy)]
@@ -11,7 +12,7 @@ pub struct Movement {
     to: usize,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct Ship {
     stacks: Vec<Vec<Crate>>,
 }
@@ -67,27 +68,43 @@ impl Ship {
 }
 
 
+fn parse_movement(idx: usize, line: &str, stack_count: usize) -> Result<Movement, ParseError> {
+    let mut tokens = line.split(' ');
+    expect_token(&mut tokens, idx, line, "move")?;
+    let quantity = parse(idx, line, next_token(&mut tokens, idx, line, "a quantity")?, "a quantity")?;
+    expect_token(&mut tokens, idx, line, "from")?;
+    let from = parse_stack(idx, line, next_token(&mut tokens, idx, line, "a stack number")?, stack_count)?;
+    expect_token(&mut tokens, idx, line, "to")?;
+    let to = parse_stack(idx, line, next_token(&mut tokens, idx, line, "a stack number")?, stack_count)?;
+    Ok(Movement { quantity, from, to })
+}
+
+/// Parses a 1-based stack number into an index below `stack_count`.
+fn parse_stack(idx: usize, line: &str, token: &str, stack_count: usize) -> Result<usize, ParseError> {
+    parse::<usize>(idx, line, token, "a stack number")?
+        .checked_sub(1)
+        .filter(|&stack| stack < stack_count)
+        .ok_or_else(|| ParseError::new(format!("a stack number between 1 and {}", stack_count), token).at_token(idx, line, token))
+}
+
 #[aoc_generator(day5)]
-pub fn input_generator(input: &str) -> (Ship, Vec<Movement>) {
+pub fn input_generator(input: &str) -> Result<(Ship, Vec<Movement>), ParseError> {
     let mut ship = Ship::new();
     let mut procedure: Vec<Movement> = vec![];
-    for l in input.lines() {
+    for (i, l) in input.lines().enumerate() {
         let s = l.trim_end();
-        if s.starts_with("[") || s.starts_with("  ") {
+        if s.starts_with('[') || s.starts_with("  ") {
             s.chars()
                 .enumerate()
                 .filter(|(j, c)| j % 4 == 1 && *c != ' ')
                 .for_each(|(j, c)| ship.add(j / 4, Crate(c)))
-        } else if s.starts_with("m") {
-            let split: Vec<&str> = s.split(" ").collect();
-            procedure.push(Movement {
-                quantity: split[1].parse().unwrap(),
-                from: split[3].parse::<usize>().unwrap() - 1,
-                to: split[5].parse::<usize>().unwrap() - 1,
-            });
+        } else if s.starts_with('m') {
+            procedure.push(parse_movement(i, s, ship.stacks.len())?);
+        } else if !s.chars().all(|c| c == ' ' || c.is_ascii_digit()) {
+            return Err(ParseError::new("a crate row, stack labels or a move", s).at(i, 0));
         }
     }
-    return (ship, procedure);
+    Ok((ship, procedure))
 }
 
 pub fn solve(input: &Ship, procedure: &[Movement], is_9001: bool) -> String {
@@ -123,13 +140,21 @@ move 3 from 1 to 3
 move 2 from 2 to 1
 move 1 from 1 to 2";
 
+    #[test]
+    fn invalid_moves() {
+        let e = input_generator("[A]\n 1\n\nmove 1 from 9 to 1").unwrap_err();
+        assert_eq!((e.line, e.column, e.expected.as_str()), (4, 13, "a stack number between 1 and 1"));
+        let e = input_generator(&TEST_INPUT.replace("to 2", "to 0")).unwrap_err();
+        assert_eq!((e.line, e.column), (9, 18));
+    }
+
     #[test]
     fn part1() {
-        assert_eq!(solve_part1(&input_generator(TEST_INPUT)), "CMZ")
+        assert_eq!(solve_part1(&input_generator(TEST_INPUT).unwrap()), "CMZ")
     }
 
     #[test]
     fn part2() {
-        assert_eq!(solve_part2(&input_generator(TEST_INPUT)), "MCD")
+        assert_eq!(solve_part2(&input_generator(TEST_INPUT).unwrap()), "MCD")
     }
 }
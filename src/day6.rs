@@ -1,8 +1,17 @@
 use aoc_runner_derive::{aoc, aoc_generator};
+use crate::parse::ParseError;
 
 #[aoc_generator(day6)]
-pub fn input_generator(input: &str) -> String {
-    input.to_owned()
+pub fn input_generator(input: &str) -> Result<String, ParseError> {
+    let s = input.trim_end();
+    match s.char_indices().find(|(_, c)| !c.is_ascii_lowercase()) {
+        Some((j, c)) => {
+            let line_start = s[..j].rfind('\n').map_or(0, |n| n + 1);
+            Err(ParseError::new("a lowercase letter", c.escape_default().to_string())
+                .at(s[..j].matches('\n').count(), j - line_start))
+        }
+        None => Ok(s.to_owned())
+    }
 }
 
 fn all_different(chars: &[u8]) -> bool {
@@ -41,27 +50,27 @@ mod tests {
 
     #[test]
     fn all_diff() {
-        assert_eq!(all_different(&vec![1, 2, 3, 4]), true);
-        assert_eq!(all_different(&vec![1, 2, 4, 4]), false);
-        assert_eq!(all_different(&vec![1, 3, 3, 4]), false);
-        assert_eq!(all_different(&vec![1, 2, 3, 1]), false);
-        assert_eq!(all_different(&vec![1, 4, 3, 4]), false);
+        assert!(all_different(&[1, 2, 3, 4]));
+        assert!(!all_different(&[1, 2, 4, 4]));
+        assert!(!all_different(&[1, 3, 3, 4]));
+        assert!(!all_different(&[1, 2, 3, 1]));
+        assert!(!all_different(&[1, 4, 3, 4]));
     }
     #[test]
     fn part1() {
-        assert_eq!(solve_part1(&input_generator("mjqjpqmgbljsphdztnvjfqwrcgsmlb")), 7);
-        assert_eq!(solve_part1(&input_generator("bvwbjplbgvbhsrlpgdmjqwftvncz")), 5);
-        assert_eq!(solve_part1(&input_generator("nppdvjthqldpwncqszvftbrmjlhg")), 6);
-        assert_eq!(solve_part1(&input_generator("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg")), 10);
-        assert_eq!(solve_part1(&input_generator("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw")), 11);
+        assert_eq!(solve_part1(&input_generator("mjqjpqmgbljsphdztnvjfqwrcgsmlb").unwrap()), 7);
+        assert_eq!(solve_part1(&input_generator("bvwbjplbgvbhsrlpgdmjqwftvncz").unwrap()), 5);
+        assert_eq!(solve_part1(&input_generator("nppdvjthqldpwncqszvftbrmjlhg").unwrap()), 6);
+        assert_eq!(solve_part1(&input_generator("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg").unwrap()), 10);
+        assert_eq!(solve_part1(&input_generator("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw").unwrap()), 11);
     }
 
     #[test]
     fn part2() {
-        assert_eq!(solve_part2(&input_generator("mjqjpqmgbljsphdztnvjfqwrcgsmlb")), 19);
-        assert_eq!(solve_part2(&input_generator("bvwbjplbgvbhsrlpgdmjqwftvncz")), 23);
-        assert_eq!(solve_part2(&input_generator("nppdvjthqldpwncqszvftbrmjlhg")), 23);
-        assert_eq!(solve_part2(&input_generator("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg")), 29);
-        assert_eq!(solve_part2(&input_generator("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw")), 26);
+        assert_eq!(solve_part2(&input_generator("mjqjpqmgbljsphdztnvjfqwrcgsmlb").unwrap()), 19);
+        assert_eq!(solve_part2(&input_generator("bvwbjplbgvbhsrlpgdmjqwftvncz").unwrap()), 23);
+        assert_eq!(solve_part2(&input_generator("nppdvjthqldpwncqszvftbrmjlhg").unwrap()), 23);
+        assert_eq!(solve_part2(&input_generator("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg").unwrap()), 29);
+        assert_eq!(solve_part2(&input_generator("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw").unwrap()), 26);
     }
 }
@@ -1,14 +1,25 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use aoc_runner_derive::{aoc, aoc_generator};
+use crate::parse::{next_token, parse, ParseError};
+
+#[derive(Debug)]
+enum Kind {
+    File,
+    Dir { children: Vec<usize> },
+}
 
 #[derive(Debug)]
 struct Node {
     name: String,
+    parent: usize,
+    kind: Kind,
+    /// Size of a file, or total size of a directory once `compute_sizes` ran.
     size: usize,
-    idx: usize,
-    parent_idx: usize,
-    children_count: usize,
 }
 
+/// A filesystem whose root directory is the node 0. Children are always stored after their
+/// parent.
 #[derive(Debug)]
 pub struct Tree {
     nodes: Vec<Node>,
@@ -20,103 +31,418 @@ impl Tree {
         Tree {
             nodes: vec![Node {
                 name: "".into(),
+                parent: 0,
+                kind: Kind::Dir { children: vec![] },
                 size: 0,
-                idx: 0,
-                parent_idx: 0,
-                children_count: 0
             }],
             current: 0,
         }
     }
 
-    fn add_file(&mut self, name: &str, size: usize) {
+    /// Adds an entry to `parent`, an entry listed again must match the previous listing.
+    fn add(&mut self, parent: usize, name: &str, kind: Kind, size: usize) -> Result<usize, ParseError> {
+        if let Some(existing) = self.child(parent, name) {
+            let node = &self.nodes[existing];
+            return match (&node.kind, &kind) {
+                (Kind::Dir { .. }, Kind::Dir { .. }) => Ok(existing),
+                (Kind::File, Kind::File) if node.size == size => Ok(existing),
+                _ => Err(ParseError::new(format!("`{}` as listed before", self.entry(existing)), name)),
+            };
+        }
+        let idx = self.nodes.len();
         self.nodes.push(Node {
             name: name.to_string(),
+            parent,
+            kind,
             size,
-            idx: self.nodes.len(),
-            parent_idx: self.current,
-            children_count: 0
         });
-        self.nodes[self.current].children_count += 1;
+        if let Kind::Dir { children } = &mut self.nodes[parent].kind {
+            children.push(idx)
+        }
+        Ok(idx)
     }
 
-    fn add_dir(&mut self, name: &str) {
-        self.nodes.push(Node {
-            name: name.to_string(),
-            size: 0,
-            idx: self.nodes.len(),
-            parent_idx: self.current,
-            children_count: 0
-        });
-        self.nodes[self.current].children_count += 1;
+    fn add_file(&mut self, name: &str, size: usize) -> Result<usize, ParseError> {
+        self.add(self.current, name, Kind::File, size)
     }
 
-    fn cd(&mut self, path: &str) {
-        if path == ".." {
-            self.current = self.nodes[self.current].parent_idx
-        } else if path == "/" {
-            self.current = 0
-        } else {
-            self.current = self.nodes.iter()
-                .skip(self.current + 1)
-                .find(|n| n.name == path && n.parent_idx == self.current)
-                .unwrap().idx;
-        }
-    }
-
-    fn size(&self, idx: usize) -> usize {
-        let n = &self.nodes[idx];
-        if n.size == 0 { // directory
-            self.nodes.iter()
-                .enumerate()
-                .skip(idx + 1)
-                .skip_while(|(_, n)| n.parent_idx != idx)
-                .take(n.children_count)
-                .map(|(i, _)| self.size(i))
-                .sum()
-        } else {
-            n.size
+    fn add_dir(&mut self, name: &str) -> Result<usize, ParseError> {
+        self.add(self.current, name, Kind::Dir { children: vec![] }, 0)
+    }
+
+    /// Changes the current directory to `path`, either absolute or relative. Directories never
+    /// listed are created with `UnknownDir::Create`.
+    ///
+    /// `path` is a slice of the line `idx` of the log, errors are located on the failing directory.
+    fn cd(&mut self, idx: usize, line: &str, path: &str, unknown_dir: UnknownDir) -> Result<(), ParseError> {
+        let mut current = if path.starts_with('/') { 0 } else { self.current };
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            current = match name {
+                "." => current,
+                ".." => self.nodes[current].parent,
+                _ => match self.child(current, name) {
+                    Some(c) if self.is_dir(c) => c,
+                    Some(_) => return Err(ParseError::new("a directory", name).at_token(idx, line, name)),
+                    None if unknown_dir == UnknownDir::Create => self.add(current, name, Kind::Dir { children: vec![] }, 0)
+                        .map_err(|e| e.at_token(idx, line, name))?,
+                    None => return Err(ParseError::new("an existing directory", name).at_token(idx, line, name)),
+                },
+            };
+        }
+        self.current = current;
+        Ok(())
+    }
+
+    /// The listing line of `idx`.
+    fn entry(&self, idx: usize) -> String {
+        match self.nodes[idx].kind {
+            Kind::File => format!("{} {}", self.size(idx), self.name(idx)),
+            Kind::Dir { .. } => format!("dir {}", self.name(idx)),
+        }
+    }
+
+    /// Adds the size of every node to its parent, children being stored after their parent.
+    fn compute_sizes(&mut self) {
+        for idx in (1..self.nodes.len()).rev() {
+            let (parent, size) = (self.nodes[idx].parent, self.nodes[idx].size);
+            self.nodes[parent].size += size;
+        }
+    }
+
+    pub fn name(&self, idx: usize) -> &str {
+        &self.nodes[idx].name
+    }
+
+    pub fn is_dir(&self, idx: usize) -> bool {
+        matches!(self.nodes[idx].kind, Kind::Dir { .. })
+    }
+
+    pub fn children(&self, idx: usize) -> &[usize] {
+        match &self.nodes[idx].kind {
+            Kind::Dir { children } => children,
+            Kind::File => &[],
+        }
+    }
+
+    /// The child of `idx` named `name`, if any.
+    pub fn child(&self, idx: usize, name: &str) -> Option<usize> {
+        self.children(idx).iter().copied().find(|&c| self.nodes[c].name == name)
+    }
+
+    /// Size of a file, or total size of the files below a directory.
+    pub fn size(&self, idx: usize) -> usize {
+        self.nodes[idx].size
+    }
+
+    /// All directories, the root first.
+    pub fn dirs(&self) -> impl Iterator<Item=usize> + '_ {
+        (0..self.nodes.len()).filter(|&i| self.is_dir(i))
+    }
+
+    /// The node at the absolute `path`, like `/a/e/i`.
+    pub fn resolve(&self, path: &str) -> Option<usize> {
+        path.strip_prefix('/')?
+            .split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(0, |idx, name| self.child(idx, name))
+    }
+
+    /// The absolute path of `idx`, `/` for the root.
+    pub fn path(&self, idx: usize) -> String {
+        let mut names = vec![];
+        let mut current = idx;
+        while current != 0 {
+            names.push(self.name(current));
+            current = self.nodes[current].parent;
         }
+        names.reverse();
+        format!("/{}", names.join("/"))
+    }
+
+    fn fmt_node(&self, f: &mut Formatter<'_>, idx: usize, depth: usize) -> std::fmt::Result {
+        let name = if idx == 0 { "/" } else { self.name(idx) };
+        write!(f, "{:indent$}- {} ", "", name, indent = 2 * depth)?;
+        match &self.nodes[idx].kind {
+            Kind::File => writeln!(f, "(file, size={})", self.size(idx)),
+            Kind::Dir { children } => {
+                writeln!(f, "(dir)")?;
+                children.iter().try_for_each(|&c| self.fmt_node(f, c, depth + 1))
+            }
+        }
+    }
+
+    /// Total size and path of every directory, largest first, one per line like `du -s`.
+    pub fn du(&self) -> String {
+        let mut dirs = self.dirs().map(|d| (self.size(d), self.path(d))).collect::<Vec<(usize, String)>>();
+        dirs.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        dirs.iter().map(|(size, path)| format!("{}\t{}\n", size, path)).collect()
     }
 }
 
+/// Draws the tree like the puzzle statement, children in the order they were first listed.
+impl Display for Tree {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_node(f, 0, 0)
+    }
+}
 
-#[aoc_generator(day7)]
-pub fn input_generator(input: &str) -> Tree {
+
+/// What `cd` does with a directory that was never listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownDir {
+    Reject,
+    Create,
+}
+
+/// Rebuilds the filesystem from a terminal log.
+///
+/// Directories can be listed several times as long as the listings agree, entries must follow
+/// an `ls` and `cd` accepts absolute and relative paths, `..` included.
+pub fn interpret(input: &str, unknown_dir: UnknownDir) -> Result<Tree, ParseError> {
     let mut tree = Tree::new();
-    input.lines().for_each(|l| {
+    let mut listing = false;
+    for (i, l) in input.lines().enumerate() {
         let s = l.trim_end();
-        let split = s.split(" ").collect::<Vec<&str>>();
-        if split[0] == "$" {
-            if split[1] == "cd" {
-                tree.cd(split[2])
+        let mut split = s.split(' ');
+        let first = next_token(&mut split, i, s, "a command or an entry")?;
+        if first == "$" {
+            let command = next_token(&mut split, i, s, "a command")?;
+            match command {
+                "cd" => {
+                    let path = next_token(&mut split, i, s, "a directory")?;
+                    tree.cd(i, s, path, unknown_dir)?;
+                    listing = false
+                }
+                "ls" => listing = true,
+                _ => return Err(ParseError::new("`cd` or `ls`", command).at_token(i, s, command))
             }
-        } else if split[0] == "dir" {
-            tree.add_dir(split[1])
+            if let Some(token) = split.next() {
+                return Err(ParseError::new("end of line", token).at_token(i, s, token));
+            }
+            continue;
+        }
+        if !listing {
+            return Err(ParseError::new("a command", first).at_token(i, s, first));
+        }
+        if first == "dir" {
+            let name = next_token(&mut split, i, s, "a directory name")?;
+            tree.add_dir(name).map_err(|e| e.at_token(i, s, name))?;
         } else {
-            tree.add_file(split[1].into(), split[0].parse().unwrap())
+            let size = parse(i, s, first, "a file size or `dir`")?;
+            let name = next_token(&mut split, i, s, "a file name")?;
+            tree.add_file(name, size).map_err(|e| e.at_token(i, s, name))?;
+        }
+        if let Some(token) = split.next() {
+            return Err(ParseError::new("end of line", token).at_token(i, s, token));
+        }
+    }
+    tree.compute_sizes();
+    Ok(tree)
+}
+
+#[aoc_generator(day7)]
+pub fn input_generator(input: &str) -> Result<Tree, ParseError> {
+    interpret(input, UnknownDir::Reject)
+}
+
+/// A directory to delete.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deletion {
+    pub name: String,
+    pub path: String,
+    pub size: usize,
+}
+
+impl Deletion {
+    fn new(tree: &Tree, idx: usize) -> Self {
+        Deletion {
+            name: if idx == 0 { "/".into() } else { tree.name(idx).to_string() },
+            path: tree.path(idx),
+            size: tree.size(idx),
+        }
+    }
+}
+
+/// Directories to delete together, none of them inside another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    pub deletions: Vec<Deletion>,
+    pub freed: usize,
+}
+
+/// Finds directories to delete to get `required` free space on a disk of size `capacity`.
+#[derive(Debug, Clone, Copy)]
+pub struct DiskPlanner {
+    pub capacity: usize,
+    pub required: usize,
+}
+
+impl Default for DiskPlanner {
+    fn default() -> Self {
+        DiskPlanner { capacity: 70000000, required: 30000000 }
+    }
+}
+
+impl DiskPlanner {
+    pub fn new(capacity: usize, required: usize) -> Self {
+        DiskPlanner { capacity, required }
+    }
+
+    /// Space that still has to be freed, 0 if there already is enough. Saturates at `usize::MAX`
+    /// minus the capacity, which no directory can free anyway.
+    pub fn missing(&self, tree: &Tree) -> usize {
+        self.required.saturating_add(tree.size(0)).saturating_sub(self.capacity)
+    }
+
+    /// The smallest directory freeing enough space on its own, `None` if even `/` is too small.
+    pub fn smallest_dir(&self, tree: &Tree) -> Option<Deletion> {
+        let missing = self.missing(tree);
+        tree.dirs()
+            .filter(|&d| tree.size(d) >= missing)
+            .min_by_key(|&d| tree.size(d))
+            .map(|d| Deletion::new(tree, d))
+    }
+
+    /// The directories freeing enough space while deleting as little as possible, `None` if even
+    /// `/` is too small.
+    ///
+    /// This is a subset sum over the directories in preorder, where deleting a directory jumps
+    /// past its subtree. `reach[k]` holds the amounts below `missing` that can be freed with the
+    /// directories before the `k`-th one, as a bitset. `via[s]` is the directory deleted last to
+    /// free `s` with the fewest directories considered, i.e. for the smallest `k` such that `s` is
+    /// in `reach[k]`, which is enough to rebuild a set of non-overlapping directories.
+    ///
+    /// The cost is pseudo-polynomial in `missing`: every directory shifts a bitset of `missing`
+    /// bits, and `via` alone takes 4 bytes per unit of `missing`, plus `missing / 8` bytes for
+    /// each subtree being walked. That is about 35 MB on a typical puzzle input and up to 120 MB
+    /// when nearly all of the default `required` is missing, `smallest_dir` being the cheap
+    /// alternative.
+    pub fn optimal_set(&self, tree: &Tree) -> Option<Plan> {
+        let missing = self.missing(tree);
+        if missing == 0 {
+            return Some(Plan { deletions: vec![], freed: 0 });
+        }
+        if tree.size(0) < missing {
+            return None;
+        }
+        let (order, end) = preorder(tree);
+        let words = missing.div_ceil(64);
+        let mut via = vec![u32::MAX; missing];
+        // The smallest `k` such that `s` is in `reach[k]`, as recorded in `via`
+        let first = |via: &[u32], s: usize| match s {
+            0 => 0,
+            _ => end.get(via[s] as usize).copied().unwrap_or(usize::MAX),
+        };
+        let mut pending = HashMap::<usize, Vec<u64>>::new();
+        let mut reach = vec![0u64; words];
+        reach[0] = 1;
+        // (freed, last deleted directory, amount freed before it)
+        let mut best: Option<(usize, usize, usize)> = None;
+        for (k, &dir) in order.iter().enumerate() {
+            let size = tree.size(dir);
+            if size > 0 {
+                if let Some(s) = next_bit(&reach, missing.saturating_sub(size)) {
+                    if best.is_none_or(|(b, _, _)| s + size < b) {
+                        best = Some((s + size, k, s));
+                    }
+                }
+                let target = pending.entry(end[k]).or_insert_with(|| vec![0; words]);
+                for (w, t) in target.iter_mut().enumerate().skip(size / 64) {
+                    let shifted = shifted_word(&reach, size, w) & mask(missing, w);
+                    // Amounts already in `reach[k]` or `reach[end[k]]` have a smaller `first`
+                    let mut new = shifted & !*t & !reach[w];
+                    while new != 0 {
+                        let s = w * 64 + new.trailing_zeros() as usize;
+                        if first(&via, s) > end[k] {
+                            via[s] = k as u32;
+                        }
+                        new &= new - 1;
+                    }
+                    *t |= shifted;
+                }
+            }
+            if let Some(next) = pending.remove(&(k + 1)) {
+                reach.iter_mut().zip(next).for_each(|(r, n)| *r |= n);
+            }
+        }
+        let (freed, k, mut s) = best?;
+        let mut dirs = vec![order[k]];
+        while s != 0 {
+            let dir = order[via[s] as usize];
+            dirs.push(dir);
+            s -= tree.size(dir);
         }
-    });
-    tree
+        Some(Plan {
+            deletions: dirs.into_iter().rev().map(|d| Deletion::new(tree, d)).collect(),
+            freed,
+        })
+    }
+}
+
+/// The directories in preorder, and for each one the position following its subtree.
+fn preorder(tree: &Tree) -> (Vec<usize>, Vec<usize>) {
+    fn visit(tree: &Tree, dir: usize, order: &mut Vec<usize>, end: &mut Vec<usize>) {
+        let k = order.len();
+        order.push(dir);
+        end.push(0);
+        for &c in tree.children(dir).iter().filter(|&&c| tree.is_dir(c)) {
+            visit(tree, c, order, end);
+        }
+        end[k] = order.len();
+    }
+    let (mut order, mut end) = (vec![], vec![]);
+    visit(tree, 0, &mut order, &mut end);
+    (order, end)
+}
+
+/// Word `w` of `bits` shifted left by `shift` bits.
+fn shifted_word(bits: &[u64], shift: usize, w: usize) -> u64 {
+    let (words, offset) = (shift / 64, shift % 64);
+    let low = bits[w - words];
+    if offset == 0 {
+        low
+    } else if w > words {
+        (low << offset) | (bits[w - words - 1] >> (64 - offset))
+    } else {
+        low << offset
+    }
+}
+
+/// The valid bits of word `w` of a bitset of `len` bits.
+fn mask(len: usize, w: usize) -> u64 {
+    match len - w * 64 {
+        n if n >= 64 => u64::MAX,
+        n => (1 << n) - 1,
+    }
+}
+
+/// The smallest set bit of `bits` at or after `from`.
+fn next_bit(bits: &[u64], from: usize) -> Option<usize> {
+    let mut w = from / 64;
+    let mut word = *bits.get(w)? & (u64::MAX << (from % 64));
+    while word == 0 {
+        w += 1;
+        word = *bits.get(w)?;
+    }
+    Some(w * 64 + word.trailing_zeros() as usize)
+}
+
+/// Total size of the directories of at most `max_size`, counting nested directories again.
+pub fn small_dirs_total(tree: &Tree, max_size: usize) -> usize {
+    tree.dirs()
+        .map(|i| tree.size(i))
+        .filter(|s| *s <= max_size)
+        .sum()
 }
 
 #[aoc(day7, part1)]
 pub fn solve_part1(input: &Tree) -> usize {
-    input.nodes.iter().enumerate()
-        .filter(|(_, n)| n.size == 0)
-        .map(|(i, _)| input.size(i))
-        .filter(|s| *s <= 100000)
-        .sum()
+    small_dirs_total(input, 100000)
 }
 
 #[aoc(day7, part2)]
-pub fn solve_part2(input: &Tree) -> usize {
-    let min_size = 30000000 - (70000000 - input.size(0));
-    input.nodes.iter().enumerate()
-        .filter(|(_, n)| n.size == 0)
-        .map(|(i, _)| input.size(i))
-        .filter(|s| *s >= min_size)
-        .min().unwrap()
+pub fn solve_part2(input: &Tree) -> Option<usize> {
+    DiskPlanner::default().smallest_dir(input).map(|d| d.size)
 }
 
 #[cfg(test)]
@@ -149,19 +475,148 @@ $ ls
 
     #[test]
     fn size() {
-        assert_eq!(input_generator(TEST_INPUT).size(5), 584);
-        assert_eq!(input_generator(TEST_INPUT).size(1), 94853);
-        assert_eq!(input_generator(TEST_INPUT).size(4), 24933642);
-        assert_eq!(input_generator(TEST_INPUT).size(0), 48381165);
+        assert_eq!(input_generator(TEST_INPUT).unwrap().size(5), 584);
+        assert_eq!(input_generator(TEST_INPUT).unwrap().size(1), 94853);
+        assert_eq!(input_generator(TEST_INPUT).unwrap().size(4), 24933642);
+        assert_eq!(input_generator(TEST_INPUT).unwrap().size(0), 48381165);
+    }
+
+    #[test]
+    fn paths() {
+        let tree = input_generator(TEST_INPUT).unwrap();
+        assert_eq!(tree.resolve("/"), Some(0));
+        let i = tree.resolve("/a/e/i").unwrap();
+        assert_eq!((tree.name(i), tree.size(i), tree.is_dir(i)), ("i", 584, false));
+        assert_eq!(tree.path(i), "/a/e/i");
+        assert_eq!(tree.resolve("/a/e/"), tree.resolve("/a/e"));
+        assert_eq!(tree.resolve("/a/x"), None);
+        assert_eq!(tree.resolve("/b.txt/x"), None);
+        assert_eq!(tree.resolve("a"), None);
+        assert_eq!(tree.dirs().map(|d| tree.path(d)).collect::<Vec<String>>(), vec!["/", "/a", "/d", "/a/e"]);
+    }
+
+    #[test]
+    fn empty_files() {
+        let tree = input_generator("$ cd /\n$ ls\n0 empty\ndir a\n$ cd a\n$ ls\n0 empty").unwrap();
+        assert_eq!(tree.dirs().count(), 2);
+        assert_eq!(tree.size(0), 0);
+        assert!(!tree.is_dir(tree.resolve("/a/empty").unwrap()));
+        let e = input_generator("$ cd /\n$ ls\n0 empty\n$ cd empty").unwrap_err();
+        assert_eq!((e.line, e.column), (4, 6));
+    }
+
+    #[test]
+    fn render() {
+        assert_eq!(input_generator(TEST_INPUT).unwrap().to_string(), "- / (dir)
+  - a (dir)
+    - e (dir)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir)
+    - j (file, size=4060174)
+    - d.log (file, size=8033020)
+    - d.ext (file, size=5626152)
+    - k (file, size=7214296)
+");
+    }
+
+    #[test]
+    fn du() {
+        assert_eq!(input_generator(TEST_INPUT).unwrap().du(), "48381165\t/
+24933642\t/d
+94853\t/a
+584\t/a/e
+");
+    }
+
+    #[test]
+    fn relisting() {
+        let input = format!("{}\n$ cd /a\n$ ls\ndir e\n29116 f\n$ cd /\n$ ls\ndir a", TEST_INPUT);
+        let tree = input_generator(&input).unwrap();
+        assert_eq!(tree.to_string(), input_generator(TEST_INPUT).unwrap().to_string());
+        assert_eq!(tree.size(0), 48381165);
+
+        let e = input_generator(&format!("{}\n$ cd /a\n$ ls\n29117 f", TEST_INPUT)).unwrap_err();
+        assert_eq!((e.line, e.column, e.expected.as_str()), (26, 7, "`29116 f` as listed before"));
+        let e = input_generator(&format!("{}\n$ cd /a\n$ ls\ndir f", TEST_INPUT)).unwrap_err();
+        assert_eq!((e.line, e.column), (26, 5));
+    }
+
+    #[test]
+    fn cd() {
+        let input = "$ cd /\n$ ls\ndir a\n$ cd a/b\n$ ls\n10 x\n$ cd /a/../a/./c\n$ ls\n5 y\n$ cd ../..\n$ ls\n1 z";
+        let e = input_generator(input).unwrap_err();
+        assert_eq!((e.line, e.column, e.text.as_str()), (4, 8, "b"));
+        let tree = interpret(input, UnknownDir::Create).unwrap();
+        assert_eq!(tree.size(tree.resolve("/a").unwrap()), 15);
+        assert_eq!(tree.size(0), 16);
+        assert_eq!(tree.path(tree.resolve("/a/c/y").unwrap()), "/a/c/y");
+
+        let e = input_generator("$ cd /\n$ ls\n1 a\n$ cd /a").unwrap_err();
+        assert_eq!((e.line, e.expected.as_str()), (4, "a directory"));
+    }
+
+    #[test]
+    fn inconsistent_log() {
+        let e = input_generator("$ cd /\ndir a").unwrap_err();
+        assert_eq!((e.line, e.column, e.expected.as_str()), (2, 1, "a command"));
+        let e = input_generator("$ cd /\n$ ls\ndir a\n$ cd a\n1 b").unwrap_err();
+        assert_eq!(e.line, 5);
+        let e = input_generator("$ ls -l").unwrap_err();
+        assert_eq!((e.line, e.column, e.text.as_str()), (1, 6, "-l"));
     }
 
     #[test]
     fn part1() {
-        assert_eq!(solve_part1(&input_generator(TEST_INPUT)), 95437);
+        assert_eq!(solve_part1(&input_generator(TEST_INPUT).unwrap()), 95437);
     }
 
     #[test]
     fn part2() {
-        assert_eq!(solve_part2(&input_generator(TEST_INPUT)), 24933642);
+        assert_eq!(solve_part2(&input_generator(TEST_INPUT).unwrap()), Some(24933642));
+    }
+
+    #[test]
+    fn planner() {
+        let tree = input_generator(TEST_INPUT).unwrap();
+        let planner = DiskPlanner::default();
+        assert_eq!(planner.missing(&tree), 8381165);
+        let d = planner.smallest_dir(&tree).unwrap();
+        assert_eq!((d.name.as_str(), d.path.as_str(), d.size), ("d", "/d", 24933642));
+
+        // Deleting `e` alone frees 584, `a` frees 94853
+        let planner = DiskPlanner::new(48381165 + 30000000 - 600, 30000000);
+        assert_eq!(planner.smallest_dir(&tree).unwrap().path, "/a");
+        let plan = planner.optimal_set(&tree).unwrap();
+        assert_eq!(plan.freed, 94853);
+        let planner = DiskPlanner::new(48381165 + 30000000 - 500, 30000000);
+        assert_eq!(planner.optimal_set(&tree).unwrap().deletions, vec![Deletion { name: "e".into(), path: "/a/e".into(), size: 584 }]);
+
+        assert_eq!(DiskPlanner::new(100, 1000).smallest_dir(&tree), None);
+        assert_eq!(DiskPlanner::new(100, 1000).optimal_set(&tree), None);
+        let planner = DiskPlanner::new(1, usize::MAX);
+        assert_eq!(planner.missing(&tree), usize::MAX - 1);
+        assert_eq!(planner.optimal_set(&tree), None);
+        assert_eq!(DiskPlanner::new(usize::MAX / 2, 10).optimal_set(&tree).unwrap().freed, 0);
+    }
+
+    #[test]
+    fn optimal_set() {
+        let input = "$ cd /\n$ ls\ndir a\ndir b\ndir c\n1000 f\n$ cd a\n$ ls\n300 x\n$ cd ../b\n$ ls\n450 y\n$ cd ../c\n$ ls\n800 z\ndir d\n$ cd d\n$ ls\n10 w";
+        let tree = input_generator(input).unwrap();
+        let planner = DiskPlanner::new(tree.size(0) + 1000 - 740, 1000);
+        assert_eq!(planner.smallest_dir(&tree).unwrap().size, 810);
+        let plan = planner.optimal_set(&tree).unwrap();
+        assert_eq!(plan.freed, 750);
+        let mut paths = plan.deletions.iter().map(|d| d.path.as_str()).collect::<Vec<&str>>();
+        paths.sort();
+        assert_eq!(paths, vec!["/a", "/b"]);
+        // `/c` and `/c/d` overlap, they cannot be freed twice
+        let planner = DiskPlanner::new(tree.size(0) + 1000 - 815, 1000);
+        assert_eq!(planner.optimal_set(&tree).unwrap().freed, 1110);
     }
 }
@@ -1,143 +1,191 @@
+use std::fmt::{Display, Formatter};
+use std::ops::BitOr;
 use aoc_runner_derive::{aoc, aoc_generator};
+use crate::grid::{Direction, Grid, Position};
+use crate::image::{pgm, scale};
+use crate::parse::ParseError;
+
+/// The edges of the forest a tree can be seen from, as bitflags.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sides(u8);
+
+impl Sides {
+    pub const NONE: Sides = Sides(0);
+    pub const TOP: Sides = Sides(1);
+    pub const BOTTOM: Sides = Sides(2);
+    pub const LEFT: Sides = Sides(4);
+    pub const RIGHT: Sides = Sides(8);
+
+    /// The edge looking towards `direction` starts from.
+    fn facing(direction: Direction) -> Sides {
+        match direction {
+            Direction::Down => Sides::TOP,
+            Direction::Up => Sides::BOTTOM,
+            Direction::Right => Sides::LEFT,
+            Direction::Left => Sides::RIGHT,
+            _ => Sides::NONE,
+        }
+    }
+
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub fn contains(&self, other: Sides) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl BitOr for Sides {
+    type Output = Sides;
+
+    fn bitor(self, rhs: Sides) -> Sides {
+        Sides(self.0 | rhs.0)
+    }
+}
+
+/// `.` for a hidden tree, the bitflags as an hexadecimal digit otherwise.
+impl Display for Sides {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            0 => write!(f, "."),
+            bits => write!(f, "{:x}", bits),
+        }
+    }
+}
 
 #[derive(Default, Clone)]
 pub struct Forest {
-    trees: Vec<Vec<i8>>,
+    trees: Grid<i8>,
 }
 
 impl Forest {
-    fn count_visible(&self) -> usize {
-        let dim = self.trees.len();
-        let mut grid = vec![vec![false; dim]; dim];
-        for i in 0..dim {
-            let mut left_max = -1;
-            self.trees[i].iter().enumerate().for_each(|(j, tree)| {
-                if *tree > left_max {
-                    left_max = self.trees[i][j];
-                    grid[i][j] = true;
-                }
-            });
+    /// The first tree of every line looking towards `direction`, i.e. the edge the line starts from.
+    fn edge(&self, direction: Direction) -> Vec<Position> {
+        let (width, height) = (self.trees.width(), self.trees.height());
+        match direction {
+            Direction::Right => (0..height).map(|row| Position::new(row, 0)).collect(),
+            Direction::Left => (0..height).map(|row| Position::new(row, width - 1)).collect(),
+            Direction::Down => (0..width).map(|col| Position::new(0, col)).collect(),
+            Direction::Up => (0..width).map(|col| Position::new(height - 1, col)).collect(),
+            _ => vec![],
+        }
+    }
 
-            let mut right_max = -1;
-            self.trees[i].iter().enumerate().rev().for_each(|(j, tree)| {
-                if *tree > right_max {
-                    right_max = self.trees[i][j];
-                    grid[i][j] = true;
+    /// The edges every tree can be seen from.
+    pub fn visibility(&self) -> Grid<Sides> {
+        let mut grid = self.trees.map(|_| Sides::NONE);
+        for direction in Direction::ORTHOGONAL {
+            for start in self.edge(direction) {
+                let mut max = -1;
+                for pos in std::iter::once(start).chain(self.trees.ray(start, direction)) {
+                    if self.trees[pos] > max {
+                        max = self.trees[pos];
+                        grid[pos] = grid[pos] | Sides::facing(direction);
+                    }
                 }
-            });
+            }
+        }
+        grid
+    }
 
+    fn count_visible(&self) -> usize {
+        self.visibility().iter().filter(|(_, v)| !v.is_empty()).count()
+    }
 
-            let mut top_max = -1;
-            self.trees.iter().enumerate().for_each(|(j, line)| {
-                if line[i] > top_max {
-                    top_max = line[i];
-                    grid[j][i] = true;
+    /// Number of trees seen from every tree looking towards `direction`, up to the first one at
+    /// least as tall or the edge.
+    ///
+    /// Every line is walked from the edge `direction` points to, keeping a stack of the trees
+    /// that may still block the view, from the tallest to the most recent.
+    pub fn viewing_distances(&self, direction: Direction) -> Grid<usize> {
+        let mut distances = self.trees.map(|_| 0);
+        let mut stack: Vec<(i8, usize)> = vec![];
+        for start in self.edge(direction.opposite()) {
+            stack.clear();
+            let line = std::iter::once(start).chain(self.trees.ray(start, direction.opposite()));
+            for (i, pos) in line.enumerate() {
+                let height = self.trees[pos];
+                while stack.last().is_some_and(|&(h, _)| h < height) {
+                    stack.pop();
                 }
-            });
+                distances[pos] = stack.last().map_or(i, |&(_, j)| i - j);
+                stack.push((height, i));
+            }
+        }
+        distances
+    }
 
-            let mut bottom_max = -1;
-            self.trees.iter().enumerate().rev().for_each(|(j, line)| {
-                if line[i] > bottom_max {
-                    bottom_max = line[i];
-                    grid[j][i] = true;
+    /// The trees seen from `from` by a viewer of the given `height` looking towards each of
+    /// `directions`, e.g. `Direction::ORTHOGONAL` or `Direction::ALL`.
+    ///
+    /// Like in part 2, the view stops at the edge or at the first tree at least as tall as the
+    /// viewer, which is seen.
+    pub fn visible_from(&self, from: Position, height: i8, directions: &[Direction]) -> Vec<Position> {
+        let mut ret = vec![];
+        for &direction in directions {
+            for pos in self.trees.ray(from, direction) {
+                ret.push(pos);
+                if self.trees[pos] >= height {
+                    break;
                 }
-            });
+            }
         }
-        grid.iter().map(|line| line.iter().filter(|&v| *v).count()).sum()
+        ret
     }
 
-    fn scenic_score(&self) -> Vec<Vec<usize>> {
-        let dim = self.trees.len();
-        let mut grid = vec![vec![1usize; dim]; dim];
-        for i in 0..dim {
-            let mut left_max = -1;
-            let mut left_max_idx = 0;
-            self.trees[i].iter().enumerate().for_each(|(col, &tree)| {
-                let row = i;
-                let mut coeff = col - left_max_idx;
-                if row == 2 && col == 3 {
-                    println!("{} {} {}", tree, left_max, left_max_idx);
-                }
-                if tree > left_max {
-                    left_max = tree;
-                    left_max_idx = col;
-                    coeff = col;
-                } else if tree == left_max {
-                    left_max_idx = col;
-                }
-                grid[row][col] *= coeff;
-                println!("{},{} {} left", row, col, coeff);
-            });
-
-            let mut right_max = -1;
-            let mut right_max_idx = dim - 1;
-            self.trees[i].iter().enumerate().rev().for_each(|(col, &tree)| {
-                let row = i;
-                let mut coeff = right_max_idx - col;
-                if tree > right_max {
-                    right_max = tree;
-                    right_max_idx = col;
-                    coeff = dim - 1 - col;
-                } else if tree == right_max {
-                    right_max_idx = col;
-                }
-                grid[row][col] *= coeff;
-                println!("{},{} {} right", row, col, coeff);
-            });
-
-
-            let mut top_max = -1;
-            let mut top_max_idx = 0;
-            self.trees.iter().enumerate().for_each(|(row, line)| {
-                let col = i;
-                let tree = line[col];
-                let mut coeff = row - top_max_idx;
-                if tree > top_max {
-                    top_max = tree;
-                    top_max_idx = row;
-                    coeff = row;
-                } else if tree == top_max {
-                    top_max_idx = row;
-                }
-                grid[row][col] *= coeff;
-                println!("{},{} {} up", row, col, coeff);
-            });
-
-            let mut bottom_max = -1;
-            let mut bottom_max_idx = dim - 1;
-            self.trees.iter().enumerate().rev().for_each(|(row, line)| {
-                let col = i;
-                let tree = line[col];
-                let mut coeff = bottom_max_idx - row;
-                if tree > bottom_max {
-                    bottom_max = tree;
-                    bottom_max_idx = row;
-                    coeff = dim - 1 - row;
-                } else if tree == bottom_max {
-                    bottom_max_idx = row;
-                }
-                grid[row][col] *= coeff;
-                println!("{},{} {} down", row, col, coeff);
-            });
+    /// Product of the viewing distances in the four directions.
+    pub fn scenic_score(&self) -> Grid<usize> {
+        let mut scores = self.trees.map(|_| 1);
+        for direction in Direction::ORTHOGONAL {
+            let distances = self.viewing_distances(direction);
+            for (pos, d) in distances.iter() {
+                scores[pos] *= d;
+            }
         }
-        println!("AAAAAAA {:?}", self.trees);
-        println!("AAAAAAA {:?}", grid);
-        grid
+        scores
+    }
+
+    /// Scenic scores scaled to `0..=levels`, the best tree getting `levels`.
+    fn heat(&self, levels: usize) -> Grid<usize> {
+        let scores = self.scenic_score();
+        let max = scores.iter().map(|(_, &s)| s).max().unwrap_or(0).max(1);
+        scores.map(|&s| s * levels / max)
+    }
+
+    /// The tree heights on a greyscale background, brighter for higher scenic scores.
+    pub fn heatmap_ansi(&self) -> String {
+        let heat = self.heat(23);
+        let mut ret = String::new();
+        for (row, levels) in heat.rows().enumerate() {
+            for (col, level) in levels.iter().enumerate() {
+                let fg = if *level < 12 { 255 } else { 232 };
+                ret += &format!("\x1b[38;5;{};48;5;{}m{}", fg, 232 + level, self.trees[Position::new(row, col)]);
+            }
+            ret += "\x1b[0m\n";
+        }
+        ret
+    }
+
+    /// The scenic scores as a PGM image, white for the best tree, every tree being a `factor`
+    /// pixels wide square.
+    pub fn heatmap_pgm(&self, factor: usize) -> Vec<u8> {
+        pgm(&scale(&self.heat(255).map(|&l| l as u8), factor))
     }
 }
 
 
 #[aoc_generator(day8)]
-pub fn input_generator(input: &str) -> Forest {
-    let mut forest = Forest::default();
-    input.lines().for_each(|l| {
-        forest.trees.push(l.trim()
-            .chars()
-            .map(|c| {
-                c as i8 - '0' as i8
-            }).collect())
-    });
-    forest
+pub fn input_generator(input: &str) -> Result<Forest, ParseError> {
+    Ok(Forest {
+        trees: Grid::parse(input, |_, c| c.to_digit(10)
+            .map(|d| d as i8)
+            .ok_or_else(|| ParseError::new("a tree height (0-9)", c)))?,
+    })
 }
 
 #[aoc(day8, part1)]
@@ -148,7 +196,7 @@ pub fn solve_part1(input: &Forest) -> usize {
 #[aoc(day8, part2)]
 pub fn solve_part2(input: &Forest) -> usize {
     let grid = input.scenic_score();
-    *grid.iter().map(|line| line.iter().max().unwrap()).max().unwrap()
+    grid.iter().map(|(_, &s)| s).max().unwrap_or(0)
 }
 
 #[cfg(test)]
@@ -163,28 +211,95 @@ mod tests {
 
     #[test]
     fn part1() {
-        assert_eq!(solve_part1(&input_generator(TEST_INPUT)), 21);
+        assert_eq!(solve_part1(&input_generator(TEST_INPUT).unwrap()), 21);
     }
 
     #[test]
     fn grid() {
-        let grid = &input_generator(TEST_INPUT).clone().scenic_score();
-        let dim = grid[0].len();
+        let grid = &input_generator(TEST_INPUT).unwrap().clone().scenic_score();
+        let dim = grid.width();
         for i in 0..dim {
-            assert_eq!(grid[i][0], 0);
-            assert_eq!(grid[0][i], 0);
-            assert_eq!(grid[i][dim - 1], 0);
-            assert_eq!(grid[dim - 1][i], 0);
+            assert_eq!(grid[Position::new(i, 0)], 0);
+            assert_eq!(grid[Position::new(0, i)], 0);
+            assert_eq!(grid[Position::new(i, dim - 1)], 0);
+            assert_eq!(grid[Position::new(dim - 1, i)], 0);
+        }
+        assert_eq!(grid[Position::new(1, 1)], 1);
+        assert_eq!(grid[Position::new(1, 2)], 4);
+        assert_eq!(grid[Position::new(1, 2)], 4);
+        assert_eq!(grid[Position::new(3, 2)], 8);
+        assert_eq!(grid[Position::new(2, 3)], 2);
+    }
+
+    #[test]
+    fn viewing_distances() {
+        let forest = input_generator(TEST_INPUT).unwrap();
+        let pos = Position::new(3, 2);
+        let distances = Direction::ORTHOGONAL.map(|d| forest.viewing_distances(d)[pos]);
+        assert_eq!(distances, [2, 1, 2, 2]);
+        assert_eq!(forest.viewing_distances(Direction::Left).row(1).copied().collect::<Vec<usize>>(), vec![0, 1, 1, 1, 2]);
+        assert_eq!(forest.viewing_distances(Direction::Down).column(0).copied().collect::<Vec<usize>>(), vec![2, 1, 2, 1, 0]);
+    }
+
+    #[test]
+    fn non_square() {
+        let forest = input_generator("3037\n2551\n6533").unwrap();
+        assert_eq!(solve_part1(&forest), 12);
+        assert_eq!(forest.scenic_score().to_string().lines().collect::<Vec<&str>>(), vec!["0000", "0110", "0000"]);
+        let forest = input_generator("1234321").unwrap();
+        assert_eq!(solve_part2(&forest), 0);
+        assert_eq!(forest.viewing_distances(Direction::Right).to_string(), "1113210");
+    }
+
+    #[test]
+    fn visibility() {
+        let visibility = input_generator(TEST_INPUT).unwrap().visibility();
+        assert_eq!(visibility.to_string(), "511d9
+459.8
+f8.88
+4.6.f
+662fa");
+        let sides = visibility[Position::new(1, 2)];
+        assert!(sides.contains(Sides::TOP | Sides::RIGHT));
+        assert!(!sides.contains(Sides::LEFT));
+    }
+
+    #[test]
+    fn heatmap() {
+        let forest = input_generator(TEST_INPUT).unwrap();
+        let bytes = forest.heatmap_pgm(2);
+        let header = "P5\n10 10\n255\n";
+        assert_eq!(bytes.len(), header.len() + 100);
+        // (3, 2) scores 8, (1, 2) scores 4 and edges score 0
+        assert_eq!(bytes[header.len() + 6 * 10 + 4], 255);
+        assert_eq!(bytes[header.len() + 2 * 10 + 4], 127);
+        assert_eq!(bytes[header.len()], 0);
+
+        let ansi = forest.heatmap_ansi();
+        assert_eq!(ansi.lines().count(), 5);
+        assert!(ansi.lines().nth(3).unwrap().contains("\x1b[38;5;232;48;5;255m5"));
+    }
+
+    #[test]
+    fn line_of_sight() {
+        let forest = input_generator(TEST_INPUT).unwrap();
+        let pos = Position::new(3, 2);
+        let seen = forest.visible_from(pos, 5, &Direction::ORTHOGONAL);
+        assert_eq!(seen, vec![Position::new(2, 2), Position::new(1, 2), Position::new(4, 2),
+                              Position::new(3, 1), Position::new(3, 0), Position::new(3, 3), Position::new(3, 4)]);
+        for (pos, &distance) in forest.viewing_distances(Direction::Up).iter() {
+            assert_eq!(forest.visible_from(pos, forest.trees[pos], &[Direction::Up]).len(), distance);
         }
-        assert_eq!(grid[1][1], 1);
-        assert_eq!(grid[1][2], 4);
-        assert_eq!(grid[1][2], 4);
-        assert_eq!(grid[3][2], 8);
-        assert_eq!(grid[2][3], 2);
+
+        let seen = forest.visible_from(pos, 5, &Direction::ALL);
+        assert_eq!(seen.len(), 7 + 2 + 1 + 1 + 1);
+        assert!(seen.contains(&Position::new(2, 1)) && !seen.contains(&Position::new(1, 0)));
+        assert_eq!(forest.visible_from(pos, 10, &[Direction::UpRight]), vec![Position::new(2, 3), Position::new(1, 4)]);
+        assert_eq!(forest.visible_from(Position::new(0, 0), 9, &[Direction::Up, Direction::Left]), vec![]);
     }
 
     #[test]
     fn part2() {
-        assert_eq!(solve_part2(&input_generator(TEST_INPUT)), 8);
+        assert_eq!(solve_part2(&input_generator(TEST_INPUT).unwrap()), 8);
     }
 }
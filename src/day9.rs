@@ -1,38 +1,51 @@
-use std::cell::RefCell;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Formatter};
-use std::ops::Deref;
 use aoc_runner_derive::{aoc, aoc_generator};
+use crate::grid::Direction;
+use crate::parse::{next_token, parse, ParseError};
 
-#[derive(Clone, Copy)]
-enum Direction {
-    Left,
-    Right,
-    Up,
-    Down,
-}
-
-impl From<&str> for Direction {
-    fn from(s: &str) -> Self {
-        match s {
-            "L" => Direction::Left,
-            "R" => Direction::Right,
-            "U" => Direction::Up,
-            "D" => Direction::Down,
-            _ => panic!("SHOULD NOT HAPPEN")
-        }
+/// `(x, y)` offset of a single step, rows growing downwards while `y` grows upwards.
+fn offset(d: Direction) -> (i64, i64) {
+    let (d_row, d_col) = d.offset();
+    (d_col as i64, -d_row as i64)
+}
+
+fn parse_direction(s: &str) -> Result<Direction, ParseError> {
+    match s {
+        "L" => Ok(Direction::Left),
+        "R" => Ok(Direction::Right),
+        "U" => Ok(Direction::Up),
+        "D" => Ok(Direction::Down),
+        "UL" => Ok(Direction::UpLeft),
+        "UR" => Ok(Direction::UpRight),
+        "DL" => Ok(Direction::DownLeft),
+        "DR" => Ok(Direction::DownRight),
+        _ => Err(ParseError::new("a direction (L, R, U, D, UL, UR, DL or DR)", s))
     }
 }
 
 pub struct Instruction {
     direction: Direction,
-    steps: u8,
+    steps: usize,
+}
+
+/// How a knot follows the previous one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FollowRule {
+    /// The puzzle rule: knots touch diagonally too, a knot left behind moves one step
+    /// orthogonally or diagonally towards the previous one.
+    #[default]
+    Chebyshev,
+    /// Knots only touch orthogonally, a knot left behind moves orthogonally, along the axis it
+    /// is the farthest on, until it touches the previous one again.
+    Manhattan,
 }
 
-#[derive(Default, PartialEq, Eq, Hash, Clone)]
-struct Position {
-    x: i64,
-    y: i64,
+/// A knot position, `y` growing upwards.
+#[derive(Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Position {
+    pub x: i64,
+    pub y: i64,
 }
 
 impl Debug for Position {
@@ -42,83 +55,418 @@ impl Debug for Position {
 }
 
 impl Position {
+    pub fn new(x: i64, y: i64) -> Self {
+        Position { x, y }
+    }
+
     fn go(&mut self, d: Direction) {
-        match d {
-            Direction::Left => self.x -= 1,
-            Direction::Right => self.x += 1,
-            Direction::Up => self.y += 1,
-            Direction::Down => self.y -= 1,
-        }
+        let (d_x, d_y) = offset(d);
+        self.x += d_x;
+        self.y += d_y;
     }
 
-    fn follow(&mut self, head: &Self) {
-        let d_y = head.y - self.y;
-        let d_x = head.x - self.x;
-        if self.x == head.x {
-            if d_y.abs() > 1 {
-                self.y += d_y / d_y.abs()
-            }
-        } else if self.y == head.y {
-            if d_x.abs() > 1 {
-                self.x += d_x / d_x.abs()
-            }
-        } else {
-            if d_x.abs() > 1 || d_y.abs() > 1 {
-                self.x += d_x / d_x.abs();
-                self.y += d_y / d_y.abs()
+    /// Moves towards `head` according to `rule` if they no longer touch.
+    pub fn follow(&mut self, head: &Self, rule: FollowRule) {
+        let (d_x, d_y) = (head.x - self.x, head.y - self.y);
+        match rule {
+            FollowRule::Chebyshev => if d_x.abs() > 1 || d_y.abs() > 1 {
+                self.x += d_x.signum();
+                self.y += d_y.signum()
+            },
+            FollowRule::Manhattan => {
+                let (mut d_x, mut d_y) = (d_x, d_y);
+                while d_x.abs() + d_y.abs() > 1 {
+                    if d_x.abs() >= d_y.abs() {
+                        self.x += d_x.signum();
+                    } else {
+                        self.y += d_y.signum();
+                    }
+                    (d_x, d_y) = (head.x - self.x, head.y - self.y);
+                }
             }
         }
     }
 }
 
-struct Rope {
-    nodes: Vec<RefCell<Position>>,
-    tracker: HashSet<Position>,
+/// A set of cells recording where a knot went.
+pub trait CellSet {
+    /// Adds `pos`, returning whether it was new.
+    fn insert(&mut self, pos: Position) -> bool;
+    fn contains(&self, pos: &Position) -> bool;
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Hints that the next cells inserted are all within `bounds`.
+    fn reserve(&mut self, _bounds: Bounds) {}
+}
+
+impl CellSet for HashSet<Position> {
+    fn insert(&mut self, pos: Position) -> bool {
+        HashSet::insert(self, pos)
+    }
+
+    fn contains(&self, pos: &Position) -> bool {
+        HashSet::contains(self, pos)
+    }
+
+    fn len(&self) -> usize {
+        HashSet::len(self)
+    }
+}
+
+pub struct Rope<S = Visited> {
+    knots: Vec<Position>,
+    tracked: usize,
+    rule: FollowRule,
+    visited: S,
 }
 
 impl Rope {
-    fn new(size: usize) -> Self {
+    /// A rope of `knots` knots starting at the origin, tracking the cells visited by its tail.
+    ///
+    /// Panics if `knots` is 0.
+    pub fn new(knots: usize) -> Self {
+        assert!(knots > 0, "a rope needs at least one knot");
+        Self::tracking(knots, knots - 1)
+    }
+
+    /// Same as `new` but tracking the cells visited by the knot `tracked`, the head being 0.
+    pub fn tracking(knots: usize, tracked: usize) -> Self {
+        Self::with_cells(knots, tracked, Visited::default())
+    }
+}
+
+impl<S: CellSet> Rope<S> {
+    /// Same as `tracking` but recording the visited cells in `cells`.
+    pub fn with_cells(knots: usize, tracked: usize, mut cells: S) -> Self {
+        assert!(tracked < knots, "cannot track knot {} of a {} knots rope", tracked, knots);
+        cells.insert(Position::default());
         Self {
-            nodes: vec![RefCell::new(Position::default()); size],
-            tracker: HashSet::new(),
+            knots: vec![Position::default(); knots],
+            tracked,
+            rule: FollowRule::default(),
+            visited: cells,
         }
     }
 
-    fn apply_instructions(&mut self, intructions: &[Instruction]) {
-        for inst in intructions {
+    /// Makes the knots follow each other according to `rule` rather than the puzzle rule.
+    pub fn with_rule(mut self, rule: FollowRule) -> Self {
+        self.rule = rule;
+        self
+    }
+
+    /// The knots from the head to the tail.
+    pub fn knots(&self) -> &[Position] {
+        &self.knots
+    }
+
+    /// The cells visited by the tracked knot, starting position included.
+    pub fn visited(&self) -> &S {
+        &self.visited
+    }
+
+    /// Moves the head one step towards `direction`, every other knot following the previous one.
+    pub fn step(&mut self, direction: Direction) {
+        self.knots[0].go(direction);
+        for i in 1..self.knots.len() {
+            let (head, tail) = self.knots.split_at_mut(i);
+            tail[0].follow(&head[i - 1], self.rule);
+        }
+        self.visited.insert(self.knots[self.tracked]);
+    }
+
+    /// Applies `instructions`, letting the visited cells know beforehand the box the knots stay in,
+    /// which `Visited` uses to switch to a dense bitset.
+    pub fn apply_instructions(&mut self, instructions: &[Instruction]) {
+        // Knots only ever move towards the previous one, so none can leave the box containing the
+        // knots and the path of the head
+        let mut bounds = Bounds::new(self.knots[0]);
+        self.knots.iter().for_each(|&k| bounds.extend(k));
+        let mut head = self.knots[0];
+        for inst in instructions {
+            let (d_x, d_y) = offset(inst.direction);
+            head = Position::new(head.x + d_x * inst.steps as i64, head.y + d_y * inst.steps as i64);
+            bounds.extend(head);
+        }
+        self.visited.reserve(bounds);
+
+        for inst in instructions {
             for _ in 0..inst.steps {
-                self.nodes.windows(2).enumerate().for_each(|(i, positions)| {
-                    if i == 0 {
-                        positions[0].borrow_mut().go(inst.direction)
-                    }
-                    positions[1].borrow_mut().follow(positions[0].borrow().deref());
+                self.step(inst.direction)
+            }
+        }
+    }
+
+    /// The character drawn for knot `i`: `H` for the head, `T` for the tail of a 2 knots rope and
+    /// the knot number otherwise.
+    fn label(&self, i: usize) -> char {
+        match i {
+            0 => 'H',
+            1 if self.knots.len() == 2 => 'T',
+            _ => std::char::from_digit(i as u32, 36).unwrap_or('+'),
+        }
+    }
+
+    /// Draws the cells of `bounds` like the puzzle, the upper row first. Knots hide the ones
+    /// after them, the start `s` and, if `show_visited`, the cells visited by the tracked knot `#`.
+    pub fn render(&self, bounds: Bounds, show_visited: bool) -> String {
+        let mut ret = String::new();
+        for y in (bounds.min.y..=bounds.max.y).rev() {
+            for x in bounds.min.x..=bounds.max.x {
+                let pos = Position::new(x, y);
+                ret.push(match self.knots.iter().position(|k| *k == pos) {
+                    Some(i) => self.label(i),
+                    None if pos == Position::default() => 's',
+                    None if show_visited && self.visited.contains(&pos) => '#',
+                    None => '.',
                 });
-                self.tracker.insert(self.nodes.iter().last().unwrap().borrow().clone());
             }
+            ret.push('\n');
+        }
+        ret
+    }
+}
+
+/// A rectangle of cells, `min` and `max` included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+    pub min: Position,
+    pub max: Position,
+}
+
+impl Bounds {
+    fn new(pos: Position) -> Self {
+        Bounds { min: pos, max: pos }
+    }
+
+    fn extend(&mut self, pos: Position) {
+        self.min = Position::new(self.min.x.min(pos.x), self.min.y.min(pos.y));
+        self.max = Position::new(self.max.x.max(pos.x), self.max.y.max(pos.y));
+    }
+
+    fn contains(&self, pos: Position) -> bool {
+        (self.min.x..=self.max.x).contains(&pos.x) && (self.min.y..=self.max.y).contains(&pos.y)
+    }
+
+    fn width(&self) -> u64 {
+        self.max.x.abs_diff(self.min.x) + 1
+    }
+
+    /// Number of cells, saturating on overflow.
+    fn area(&self) -> u64 {
+        self.width().saturating_mul(self.max.y.abs_diff(self.min.y) + 1)
+    }
+}
+
+/// Side of the square chunks of sparse `Visited` sets.
+const CHUNK: i64 = 64;
+
+/// Largest box tracked by a dense `Visited` set, in cells (8 MiB).
+const DENSE_LIMIT: u64 = 1 << 26;
+
+/// A set of cells stored as bitsets, dense over a box when the cells are known to fit in a small
+/// enough one, sparse otherwise.
+#[derive(Debug, Clone, Default)]
+pub struct Visited {
+    cells: Cells,
+    len: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Cells {
+    /// A bit per cell of `bounds`, row by row.
+    Dense { bounds: Bounds, bits: Vec<u64> },
+    /// `CHUNK` x `CHUNK` bitsets allocated on demand, a `u64` per row.
+    Sparse(HashMap<(i64, i64), [u64; CHUNK as usize]>),
+}
+
+impl Default for Cells {
+    fn default() -> Self {
+        Cells::Sparse(HashMap::new())
+    }
+}
+
+impl Visited {
+    fn sparse(positions: impl IntoIterator<Item=Position>) -> Self {
+        let mut ret = Visited::default();
+        positions.into_iter().for_each(|p| { ret.insert(p); });
+        ret
+    }
+
+    /// Word index and bit mask of `pos` in a dense bitset over `bounds`.
+    fn dense_bit(bounds: &Bounds, pos: Position) -> (usize, u64) {
+        let i = (pos.y - bounds.min.y) as u64 * bounds.width() + (pos.x - bounds.min.x) as u64;
+        ((i / 64) as usize, 1 << (i % 64))
+    }
+
+    /// Chunk key, row and bit mask of `pos` in a sparse bitmap.
+    fn sparse_bit(pos: Position) -> ((i64, i64), usize, u64) {
+        let key = (pos.x.div_euclid(CHUNK), pos.y.div_euclid(CHUNK));
+        (key, pos.y.rem_euclid(CHUNK) as usize, 1 << pos.x.rem_euclid(CHUNK))
+    }
+
+    /// Whether the cells are stored as a single dense bitset.
+    pub fn is_dense(&self) -> bool {
+        matches!(self.cells, Cells::Dense { .. })
+    }
+
+    /// All cells of the set, in no particular order.
+    pub fn positions(&self) -> Vec<Position> {
+        let mut ret = vec![];
+        match &self.cells {
+            Cells::Dense { bounds, bits } => {
+                let width = bounds.width();
+                for (i, &word) in bits.iter().enumerate() {
+                    ret.extend(bits_of(word).map(|b| {
+                        let j = i as u64 * 64 + b;
+                        Position::new(bounds.min.x + (j % width) as i64, bounds.min.y + (j / width) as i64)
+                    }));
+                }
+            }
+            Cells::Sparse(chunks) => {
+                for (&(c_x, c_y), chunk) in chunks {
+                    for (row, &word) in chunk.iter().enumerate() {
+                        ret.extend(bits_of(word).map(|b| Position::new(c_x * CHUNK + b as i64, c_y * CHUNK + row as i64)));
+                    }
+                }
+            }
+        }
+        ret
+    }
+}
+
+impl CellSet for Visited {
+    /// A dense set turns sparse when `pos` is outside of its bounds.
+    fn insert(&mut self, pos: Position) -> bool {
+        let (word, mask) = match &mut self.cells {
+            Cells::Dense { bounds, bits } if bounds.contains(pos) => {
+                let (i, mask) = Self::dense_bit(bounds, pos);
+                (&mut bits[i], mask)
+            }
+            Cells::Dense { .. } => {
+                *self = Self::sparse(self.positions());
+                return self.insert(pos);
+            }
+            Cells::Sparse(chunks) => {
+                let (key, row, mask) = Self::sparse_bit(pos);
+                (&mut chunks.entry(key).or_insert([0; CHUNK as usize])[row], mask)
+            }
+        };
+        let new = *word & mask == 0;
+        *word |= mask;
+        self.len += new as usize;
+        new
+    }
+
+    fn contains(&self, pos: &Position) -> bool {
+        match &self.cells {
+            Cells::Dense { bounds, bits } => bounds.contains(*pos) && {
+                let (i, mask) = Self::dense_bit(bounds, *pos);
+                bits[i] & mask != 0
+            },
+            Cells::Sparse(chunks) => {
+                let (key, row, mask) = Self::sparse_bit(*pos);
+                chunks.get(&key).is_some_and(|c| c[row] & mask != 0)
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Switches to a dense bitset if `bounds` holds every cell of the set and is small enough.
+    fn reserve(&mut self, mut bounds: Bounds) {
+        if let Cells::Dense { bounds: current, .. } = &self.cells {
+            if current.contains(bounds.min) && current.contains(bounds.max) {
+                return;
+            }
+        }
+        let positions = self.positions();
+        positions.iter().for_each(|&p| bounds.extend(p));
+        if bounds.area() <= DENSE_LIMIT {
+            let words = bounds.area().div_ceil(64) as usize;
+            *self = Visited { cells: Cells::Dense { bounds, bits: vec![0; words] }, len: 0 };
+            positions.into_iter().for_each(|p| { self.insert(p); });
         }
     }
 }
 
+/// Indices of the set bits of `word`.
+fn bits_of(mut word: u64) -> impl Iterator<Item=u64> {
+    std::iter::from_fn(move || {
+        (word != 0).then(|| {
+            let b = word.trailing_zeros() as u64;
+            word &= word - 1;
+            b
+        })
+    })
+}
+
+/// When `animate` draws a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameRate {
+    Step,
+    Instruction,
+}
+
+/// Draws a `knots` knots rope before the first instruction and after every step or instruction,
+/// all frames sharing the smallest bounds containing the start and every knot position.
+pub fn animate(knots: usize, instructions: &[Instruction], rate: FrameRate, show_visited: bool) -> Vec<String> {
+    let mut bounds = Bounds::new(Position::default());
+    let mut rope = Rope::new(knots);
+    for inst in instructions {
+        for _ in 0..inst.steps {
+            rope.step(inst.direction);
+            rope.knots().iter().for_each(|&k| bounds.extend(k));
+        }
+    }
+
+    let mut rope = Rope::new(knots);
+    let mut frames = vec![rope.render(bounds, show_visited)];
+    for inst in instructions {
+        for _ in 0..inst.steps {
+            rope.step(inst.direction);
+            if rate == FrameRate::Step {
+                frames.push(rope.render(bounds, show_visited))
+            }
+        }
+        if rate == FrameRate::Instruction {
+            frames.push(rope.render(bounds, show_visited))
+        }
+    }
+    frames
+}
+
 #[aoc_generator(day9)]
-pub fn input_generator(input: &str) -> Vec<Instruction> {
-    input.lines().map(|l| {
-        let split: Vec<&str> = l.trim().split(" ").collect();
-        Instruction { direction: split[0].into(), steps: split[1].parse().unwrap() }
+pub fn input_generator(input: &str) -> Result<Vec<Instruction>, ParseError> {
+    input.lines().enumerate().map(|(i, l)| {
+        let s = l.trim();
+        let mut split = s.split(' ');
+        let direction = next_token(&mut split, i, l, "a direction")?;
+        let steps = next_token(&mut split, i, l, "a step count")?;
+        Ok(Instruction {
+            direction: parse_direction(direction).map_err(|e| e.at_token(i, l, direction))?,
+            steps: parse(i, l, steps, "a step count")?,
+        })
     }).collect()
 }
 
 #[aoc(day9, part1)]
-pub fn solve_part1(_input: &[Instruction]) -> usize {
+pub fn solve_part1(input: &[Instruction]) -> usize {
     let mut rope = Rope::new(2);
-    rope.apply_instructions(_input);
-    rope.tracker.len()
+    rope.apply_instructions(input);
+    rope.visited().len()
 }
 
 #[aoc(day9, part2)]
 pub fn solve_part2(input: &[Instruction]) -> usize {
     let mut rope = Rope::new(10);
     rope.apply_instructions(input);
-    rope.tracker.len()
+    rope.visited().len()
 }
 
 #[cfg(test)]
@@ -145,12 +493,140 @@ U 20";
 
     #[test]
     fn part1() {
-        assert_eq!(solve_part1(&input_generator(TEST_INPUT)), 13);
+        assert_eq!(solve_part1(&input_generator(TEST_INPUT).unwrap()), 13);
     }
 
     #[test]
     fn part2() {
-        assert_eq!(solve_part2(&input_generator(TEST_INPUT)), 1);
-        assert_eq!(solve_part2(&input_generator(TEST_INPUT2)), 36);
+        assert_eq!(solve_part2(&input_generator(TEST_INPUT).unwrap()), 1);
+        assert_eq!(solve_part2(&input_generator(TEST_INPUT2).unwrap()), 36);
+    }
+
+    #[test]
+    fn rope() {
+        let instructions = input_generator(TEST_INPUT).unwrap();
+        let mut rope = Rope::new(10);
+        rope.apply_instructions(&instructions);
+        assert_eq!(rope.knots()[0], Position::new(2, 2));
+        assert_eq!(rope.knots()[1], Position::new(1, 2));
+        assert_eq!(&rope.knots()[5..], &[Position::new(1, 1), Position::default(), Position::default(), Position::default(), Position::default()]);
+
+        // The second knot of a 10 knots rope moves like the tail of a 2 knots one
+        let mut rope = Rope::tracking(10, 1);
+        rope.apply_instructions(&instructions);
+        assert_eq!(rope.visited().len(), 13);
+        let mut rope = Rope::tracking(1, 0);
+        rope.apply_instructions(&instructions);
+        assert_eq!(rope.visited().len(), 21);
+        assert!(std::panic::catch_unwind(|| Rope::new(0)).is_err());
+    }
+
+    #[test]
+    fn animation() {
+        let instructions = input_generator(TEST_INPUT).unwrap();
+        let frames = animate(2, &instructions, FrameRate::Instruction, false);
+        assert_eq!(frames.len(), 9);
+        assert_eq!(frames[0], "......\n......\n......\n......\nH.....\n");
+        assert_eq!(frames[1], "......\n......\n......\n......\ns..TH.\n");
+        assert_eq!(frames[2], "....H.\n....T.\n......\n......\ns.....\n");
+        assert_eq!(frames[8], "......\n......\n.TH...\n......\ns.....\n");
+        assert_eq!(animate(2, &instructions, FrameRate::Step, false).len(), 25);
+
+        let visited = animate(2, &instructions, FrameRate::Instruction, true);
+        assert_eq!(visited[8], "..##..\n...##.\n.TH##.\n....#.\ns###..\n");
+    }
+
+    #[test]
+    fn render_larger_rope() {
+        let instructions = input_generator(TEST_INPUT2).unwrap();
+        let frames = animate(10, &instructions[..2], FrameRate::Instruction, false);
+        assert_eq!(frames[2], ".....H
+.....1
+.....2
+.....3
+....54
+...6..
+..7...
+.8....
+9.....
+");
+    }
+
+    #[test]
+    fn diagonals() {
+        let instructions = input_generator("UR 3\nDL 1\nR 300").unwrap();
+        assert_eq!(instructions[2].steps, 300);
+        let mut rope = Rope::new(2);
+        rope.apply_instructions(&instructions);
+        assert_eq!(rope.knots(), &[Position::new(302, 2), Position::new(301, 2)]);
+        // The tail follows the diagonal and the first step right does not move it
+        assert_eq!(rope.visited().len(), 3 + 299);
+    }
+
+    #[test]
+    fn follow_rules() {
+        let mut tail = Position::default();
+        tail.follow(&Position::new(1, 1), FollowRule::Manhattan);
+        assert_eq!(tail, Position::new(1, 0));
+        let mut tail = Position::default();
+        tail.follow(&Position::new(1, 1), FollowRule::Chebyshev);
+        assert_eq!(tail, Position::default());
+        let mut tail = Position::default();
+        tail.follow(&Position::new(2, 3), FollowRule::Manhattan);
+        assert_eq!(tail, Position::new(2, 2));
+
+        let instructions = input_generator(TEST_INPUT).unwrap();
+        let mut rope = Rope::new(2).with_rule(FollowRule::Manhattan);
+        rope.apply_instructions(&instructions);
+        // Ties move along x, so the tail ends up sliding above the head
+        assert_eq!(rope.knots(), &[Position::new(2, 2), Position::new(2, 3)]);
+        assert_eq!(rope.visited().len(), 13);
+    }
+
+    #[test]
+    fn visited() {
+        let mut sparse = Visited::sparse([]);
+        let positions = [Position::new(-1, -1), Position::new(63, 0), Position::new(64, -65), Position::new(-1, -1)];
+        assert_eq!(positions.map(|p| sparse.insert(p)), [true, true, true, false]);
+        assert_eq!(sparse.len(), 3);
+        assert!(sparse.contains(&Position::new(64, -65)) && !sparse.contains(&Position::new(0, -65)));
+
+        let mut dense = sparse.clone();
+        dense.reserve(Bounds::new(Position::default()));
+        assert!(dense.is_dense());
+        let mut cells = dense.positions();
+        cells.sort_by_key(|p| (p.x, p.y));
+        assert_eq!(cells, vec![Position::new(-1, -1), Position::new(63, 0), Position::new(64, -65)]);
+        assert!(dense.insert(Position::new(0, 0)));
+        assert!(!dense.insert(Position::new(63, 0)));
+        // Leaving the box falls back to the sparse bitmap
+        assert!(dense.insert(Position::new(1000, 1000)));
+        assert!(!dense.is_dense());
+        assert_eq!(dense.len(), 5);
+
+        let mut huge = Visited::sparse([Position::default()]);
+        huge.reserve(Bounds { min: Position::new(-1 << 40, 0), max: Position::new(1 << 40, 1 << 40) });
+        assert!(!huge.is_dense());
+
+        let instructions = input_generator(TEST_INPUT2).unwrap();
+        let mut rope = Rope::with_cells(10, 9, HashSet::new());
+        rope.apply_instructions(&instructions);
+        let mut dense = Rope::new(10);
+        dense.apply_instructions(&instructions);
+        assert!(dense.visited().is_dense());
+        assert!(rope.visited().iter().all(|p| dense.visited().contains(p)));
+        assert_eq!(rope.visited().len(), dense.visited().len());
+    }
+
+    #[test]
+    fn invalid_input() {
+        let e = input_generator("R 4\nX 2").err().unwrap();
+        assert_eq!((e.line, e.column, e.text.as_str()), (2, 1, "X"));
+        let e = input_generator("R 4\nU").err().unwrap();
+        assert_eq!((e.line, e.column), (2, 2));
+        let e = input_generator("UX 4").err().unwrap();
+        assert_eq!(e.text, "UX");
+        let e = input_generator("U -4").err().unwrap();
+        assert_eq!((e.line, e.column), (1, 3));
     }
 }
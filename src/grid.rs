@@ -0,0 +1,301 @@
+use std::fmt::{Display, Formatter};
+use std::ops::{Index, IndexMut};
+use crate::parse::ParseError;
+
+/// A cell coordinate, `row` grows downwards and `col` grows to the right.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Position {
+    pub row: usize,
+    pub col: usize,
+}
+
+impl Position {
+    pub fn new(row: usize, col: usize) -> Self {
+        Position { row, col }
+    }
+
+    /// Moves one cell towards `direction`, `None` if that leaves the first row or column.
+    pub fn step(&self, direction: Direction) -> Option<Position> {
+        let (d_row, d_col) = direction.offset();
+        Some(Position {
+            row: self.row.checked_add_signed(d_row)?,
+            col: self.col.checked_add_signed(d_col)?,
+        })
+    }
+
+    /// The direction leading to `other`, `None` unless it is one of the 8 surrounding cells.
+    pub fn direction_to(&self, other: Position) -> Option<Direction> {
+        Direction::ALL.into_iter().find(|&d| self.step(d) == Some(other))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Direction {
+    pub const ORTHOGONAL: [Direction; 4] = [Direction::Up, Direction::Down, Direction::Left, Direction::Right];
+    pub const ALL: [Direction; 8] = [
+        Direction::Up, Direction::Down, Direction::Left, Direction::Right,
+        Direction::UpLeft, Direction::UpRight, Direction::DownLeft, Direction::DownRight,
+    ];
+
+    /// `(row, col)` offset of a single step.
+    pub fn offset(&self) -> (isize, isize) {
+        match self {
+            Direction::Up => (-1, 0),
+            Direction::Down => (1, 0),
+            Direction::Left => (0, -1),
+            Direction::Right => (0, 1),
+            Direction::UpLeft => (-1, -1),
+            Direction::UpRight => (-1, 1),
+            Direction::DownLeft => (1, -1),
+            Direction::DownRight => (1, 1),
+        }
+    }
+
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::UpLeft => Direction::DownRight,
+            Direction::UpRight => Direction::DownLeft,
+            Direction::DownLeft => Direction::UpRight,
+            Direction::DownRight => Direction::UpLeft,
+        }
+    }
+}
+
+/// A rectangular grid stored row by row in a single `Vec`.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    /// Builds a grid from its cells in row-major order.
+    ///
+    /// Panics if `cells.len() != width * height`.
+    pub fn new(width: usize, height: usize, cells: Vec<T>) -> Self {
+        assert_eq!(cells.len(), width * height, "a {}x{} grid needs {} cells", width, height, width * height);
+        Grid { width, height, cells }
+    }
+
+    pub fn filled(width: usize, height: usize, value: T) -> Self where T: Clone {
+        Grid { width, height, cells: vec![value; width * height] }
+    }
+
+    /// Parses a character map, one row per line, calling `cell` on every character.
+    ///
+    /// Errors returned by `cell` without a location are located on the offending character.
+    pub fn parse(input: &str, mut cell: impl FnMut(Position, char) -> Result<T, ParseError>) -> Result<Self, ParseError> {
+        let mut width = None;
+        let mut cells = vec![];
+        let mut height = 0;
+        for (row, l) in input.lines().enumerate() {
+            let s = l.trim();
+            let mut count = 0;
+            for (col, (j, c)) in s.char_indices().enumerate() {
+                let pos = Position { row, col };
+                cells.push(cell(pos, c).map_err(|e| match e.line {
+                    0 => e.at_token(row, l, &s[j..j + c.len_utf8()]),
+                    _ => e,
+                })?);
+                count += 1;
+            }
+            match width {
+                None => width = Some(count),
+                Some(w) if w != count => return Err(ParseError::new(format!("a row of {} cells", w), s).at_token(row, l, s)),
+                _ => ()
+            }
+            height += 1;
+        }
+        Ok(Grid { width: width.unwrap_or(0), height, cells })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn contains(&self, pos: Position) -> bool {
+        pos.row < self.height && pos.col < self.width
+    }
+
+    pub fn get(&self, pos: Position) -> Option<&T> {
+        if self.contains(pos) {
+            Some(&self.cells[pos.row * self.width + pos.col])
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, pos: Position) -> Option<&mut T> {
+        if self.contains(pos) {
+            Some(&mut self.cells[pos.row * self.width + pos.col])
+        } else {
+            None
+        }
+    }
+
+    /// Moves one cell towards `direction`, `None` if that leaves the grid.
+    pub fn step(&self, pos: Position, direction: Direction) -> Option<Position> {
+        pos.step(direction).filter(|p| self.contains(*p))
+    }
+
+    /// The up to 4 orthogonally adjacent cells of `pos`.
+    pub fn neighbours4(&self, pos: Position) -> impl Iterator<Item=Position> + '_ {
+        Direction::ORTHOGONAL.into_iter().filter_map(move |d| self.step(pos, d))
+    }
+
+    /// The up to 8 cells surrounding `pos`, diagonals included.
+    pub fn neighbours8(&self, pos: Position) -> impl Iterator<Item=Position> + '_ {
+        Direction::ALL.into_iter().filter_map(move |d| self.step(pos, d))
+    }
+
+    /// The cells met walking from `from` towards `direction` up to the edge, `from` excluded.
+    pub fn ray(&self, from: Position, direction: Direction) -> impl Iterator<Item=Position> + '_ {
+        std::iter::successors(self.step(from, direction), move |p| self.step(*p, direction))
+    }
+
+    pub fn row(&self, row: usize) -> impl DoubleEndedIterator<Item=&T> {
+        self.cells[row * self.width..(row + 1) * self.width].iter()
+    }
+
+    /// Panics if `col` is not below the width.
+    pub fn column(&self, col: usize) -> impl DoubleEndedIterator<Item=&T> {
+        assert!(col < self.width, "column {} is outside of a {}x{} grid", col, self.width, self.height);
+        (0..self.height).map(move |row| &self.cells[row * self.width + col])
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item=&[T]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    /// All positions in row-major order.
+    pub fn positions(&self) -> impl Iterator<Item=Position> {
+        let width = self.width;
+        (0..self.height).flat_map(move |row| (0..width).map(move |col| Position { row, col }))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=(Position, &T)> {
+        self.positions().zip(self.cells.iter())
+    }
+
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.iter().map(f).collect(),
+        }
+    }
+}
+
+impl<T> Index<Position> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, pos: Position) -> &Self::Output {
+        assert!(self.contains(pos), "{:?} is outside of a {}x{} grid", pos, self.width, self.height);
+        &self.cells[pos.row * self.width + pos.col]
+    }
+}
+
+impl<T> IndexMut<Position> for Grid<T> {
+    fn index_mut(&mut self, pos: Position) -> &mut Self::Output {
+        assert!(self.contains(pos), "{:?} is outside of a {}x{} grid", pos, self.width, self.height);
+        &mut self.cells[pos.row * self.width + pos.col]
+    }
+}
+
+impl<T: Display> Display for Grid<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, row) in self.rows().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            for cell in row {
+                write!(f, "{}", cell)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid::*;
+
+    const TEST_INPUT: &str = "123
+456";
+
+    fn digits(input: &str) -> Result<Grid<u32>, ParseError> {
+        Grid::parse(input, |_, c| c.to_digit(10).ok_or_else(|| ParseError::new("a digit", c)))
+    }
+
+    #[test]
+    fn parse() {
+        let grid = digits(TEST_INPUT).unwrap();
+        assert_eq!((grid.width(), grid.height()), (3, 2));
+        assert_eq!(grid[Position::new(1, 0)], 4);
+        assert_eq!(grid.to_string(), TEST_INPUT);
+
+        let e = digits("123\n4x6").unwrap_err();
+        assert_eq!((e.line, e.column), (2, 2));
+        let e = digits("123\n45").unwrap_err();
+        assert_eq!(e.expected, "a row of 3 cells");
+    }
+
+    #[test]
+    fn checked_indexing() {
+        let grid = digits(TEST_INPUT).unwrap();
+        assert_eq!(grid.get(Position::new(0, 2)), Some(&3));
+        assert_eq!(grid.get(Position::new(0, 3)), None);
+        assert_eq!(grid.get(Position::new(2, 0)), None);
+    }
+
+    #[test]
+    fn neighbours() {
+        let grid = digits(TEST_INPUT).unwrap();
+        let n4 = grid.neighbours4(Position::new(0, 0)).collect::<Vec<Position>>();
+        assert_eq!(n4, vec![Position::new(1, 0), Position::new(0, 1)]);
+        assert_eq!(grid.neighbours4(Position::new(1, 1)).count(), 3);
+        assert_eq!(grid.neighbours8(Position::new(1, 1)).count(), 5);
+        assert_eq!(grid.neighbours8(Position::new(0, 0)).count(), 3);
+    }
+
+    #[test]
+    fn lines() {
+        let grid = digits(TEST_INPUT).unwrap();
+        assert_eq!(grid.row(1).copied().collect::<Vec<u32>>(), vec![4, 5, 6]);
+        assert_eq!(grid.column(2).rev().copied().collect::<Vec<u32>>(), vec![6, 3]);
+        assert!(std::panic::catch_unwind(|| grid.column(3).count()).is_err());
+        let ray = grid.ray(Position::new(0, 0), Direction::Right).map(|p| grid[p]).collect::<Vec<u32>>();
+        assert_eq!(ray, vec![2, 3]);
+        assert_eq!(grid.ray(Position::new(0, 0), Direction::DownRight).collect::<Vec<Position>>(), vec![Position::new(1, 1)]);
+        assert_eq!(grid.ray(Position::new(0, 0), Direction::Up).count(), 0);
+    }
+
+    #[test]
+    fn direction_to() {
+        let pos = Position::new(1, 1);
+        assert_eq!(pos.direction_to(Position::new(0, 1)), Some(Direction::Up));
+        assert_eq!(pos.direction_to(Position::new(2, 0)), Some(Direction::DownLeft));
+        assert_eq!(pos.direction_to(Position::new(1, 3)), None);
+        assert_eq!(pos.direction_to(pos), None);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::grid::{Grid, Position};
+
+/// Enlarges `grid` so that every cell becomes a `factor` x `factor` square.
+pub fn scale<T: Clone>(grid: &Grid<T>, factor: usize) -> Grid<T> {
+    let (width, height) = (grid.width() * factor, grid.height() * factor);
+    let cells = (0..height)
+        .flat_map(|row| (0..width).map(move |col| Position::new(row / factor, col / factor)))
+        .map(|pos| grid[pos].clone())
+        .collect();
+    Grid::new(width, height, cells)
+}
+
+/// Encodes a bitmap as a raw (binary) PBM, `true` cells being black.
+pub fn pbm(grid: &Grid<bool>) -> Vec<u8> {
+    let mut ret = format!("P4\n{} {}\n", grid.width(), grid.height()).into_bytes();
+    for row in grid.rows() {
+        for chunk in row.chunks(8) {
+            let byte = chunk.iter()
+                .enumerate()
+                .fold(0u8, |acc, (i, &bit)| acc | ((bit as u8) << (7 - i)));
+            ret.push(byte)
+        }
+    }
+    ret
+}
+
+/// Encodes a greyscale image as a raw (binary) PGM, 0 being black and 255 white.
+pub fn pgm(grid: &Grid<u8>) -> Vec<u8> {
+    let mut ret = format!("P5\n{} {}\n255\n", grid.width(), grid.height()).into_bytes();
+    for row in grid.rows() {
+        ret.extend_from_slice(row)
+    }
+    ret
+}
+
+/// Concatenates several PGM images in a single stream, as allowed by the netpbm format.
+pub fn pgm_sequence<'a>(frames: impl IntoIterator<Item=&'a Grid<u8>>) -> Vec<u8> {
+    frames.into_iter().flat_map(pgm).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::image::*;
+
+    #[test]
+    fn scaling() {
+        let grid = Grid::new(2, 1, vec![1, 2]);
+        let scaled = scale(&grid, 2);
+        assert_eq!((scaled.width(), scaled.height()), (4, 2));
+        assert_eq!(scaled.row(1).copied().collect::<Vec<u8>>(), vec![1, 1, 2, 2]);
+    }
+
+    #[test]
+    fn bitmap() {
+        let mut cells = vec![false; 10];
+        cells[0] = true;
+        cells[8] = true;
+        let bytes = pbm(&Grid::new(10, 1, cells));
+        assert_eq!(bytes, b"P4\n10 1\n\x80\x80");
+    }
+
+    #[test]
+    fn greymap() {
+        let grid = Grid::new(2, 2, vec![0, 64, 128, 255]);
+        assert_eq!(pgm(&grid), b"P5\n2 2\n255\n\x00\x40\x80\xff");
+        assert_eq!(pgm_sequence([&grid, &grid]).len(), 2 * pgm(&grid).len());
+    }
+}
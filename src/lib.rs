@@ -11,6 +11,10 @@ pub mod day10;
 pub mod day11;
 pub mod day12;
 pub mod day13;
+pub mod grid;
+pub mod image;
+pub mod ocr;
+pub mod parse;
 
 use aoc_runner_derive::aoc_lib;
 
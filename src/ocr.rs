@@ -0,0 +1,130 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use crate::day10::Pixel;
+
+pub const GLYPH_WIDTH: usize = 4;
+pub const GLYPH_HEIGHT: usize = 6;
+
+/// Glyphs are separated by a blank column.
+const GLYPH_SPACING: usize = GLYPH_WIDTH + 1;
+
+/// The capital letters of the 4x6 font drawn by the AoC CRT puzzles, one row per string.
+const FONT: [(char, [&str; GLYPH_HEIGHT]); 17] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OcrError {
+    /// The screen is not `GLYPH_HEIGHT` pixels high.
+    Height(usize),
+    /// Zero-based positions of the glyphs matching no letter.
+    UnknownGlyphs(Vec<usize>),
+}
+
+impl Display for OcrError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OcrError::Height(h) => write!(f, "expected a screen {} pixels high, found {}", GLYPH_HEIGHT, h),
+            OcrError::UnknownGlyphs(positions) => write!(f, "unknown glyphs at positions {}", positions.iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")),
+        }
+    }
+}
+
+impl Error for OcrError {}
+
+fn glyph_at(screen: &[Vec<Pixel>], idx: usize) -> Vec<String> {
+    screen.iter().map(|line| {
+        (idx * GLYPH_SPACING..idx * GLYPH_SPACING + GLYPH_WIDTH)
+            .map(|x| line.get(x).unwrap_or(&Pixel::Off).to_string())
+            .collect()
+    }).collect()
+}
+
+fn recognize_glyph(glyph: &[String]) -> Option<char> {
+    FONT.iter()
+        .find(|(_, rows)| rows.iter().zip(glyph).all(|(a, b)| a == b))
+        .map(|(c, _)| *c)
+}
+
+/// Reads the letters drawn on a CRT screen.
+pub fn recognize(screen: &[Vec<Pixel>]) -> Result<String, OcrError> {
+    if screen.len() != GLYPH_HEIGHT {
+        return Err(OcrError::Height(screen.len()));
+    }
+    let width = screen.iter().map(|line| line.len()).max().unwrap_or(0);
+    let count = width.div_ceil(GLYPH_SPACING);
+    let mut ret = String::with_capacity(count);
+    let mut unknown = vec![];
+    for idx in 0..count {
+        match recognize_glyph(&glyph_at(screen, idx)) {
+            Some(c) => ret.push(c),
+            None => unknown.push(idx),
+        }
+    }
+    if unknown.is_empty() {
+        Ok(ret)
+    } else {
+        Err(OcrError::UnknownGlyphs(unknown))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ocr::*;
+
+    fn screen(picture: &str) -> Vec<Vec<Pixel>> {
+        picture.lines().map(|l| l.chars().map(|c| match c {
+            '#' => Pixel::On,
+            _ => Pixel::Off,
+        }).collect()).collect()
+    }
+
+    const PICTURE: &str = "####.###....##.###..###..#..#..##..#..#.
+#....#..#....#.#..#.#..#.#.#..#..#.#..#.
+###..#..#....#.###..#..#.##...#..#.####.
+#....###.....#.#..#.###..#.#..####.#..#.
+#....#....#..#.#..#.#.#..#.#..#..#.#..#.
+####.#.....##..###..#..#.#..#.#..#.#..#.";
+
+    #[test]
+    fn letters() {
+        assert_eq!(recognize(&screen(PICTURE)), Ok("EPJBRKAH".to_owned()));
+    }
+
+    #[test]
+    fn font() {
+        let picture = (0..GLYPH_HEIGHT)
+            .map(|row| FONT.iter().map(|(_, rows)| rows[row]).collect::<Vec<&str>>().join("."))
+            .collect::<Vec<String>>()
+            .join("\n");
+        assert_eq!(recognize(&screen(&picture)), Ok("ABCEFGHIJKLOPRSUZ".to_owned()));
+    }
+
+    #[test]
+    fn unknown_glyphs() {
+        let picture = PICTURE.replace("####.###", "#.##.#.#");
+        let e = recognize(&screen(&picture)).unwrap_err();
+        assert_eq!(e, OcrError::UnknownGlyphs(vec![0, 1]));
+        assert_eq!(e.to_string(), "unknown glyphs at positions 0, 1");
+        assert_eq!(recognize(&screen("#")), Err(OcrError::Height(1)));
+    }
+}
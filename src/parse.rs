@@ -0,0 +1,130 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Error returned by the input generators when the puzzle input is malformed.
+///
+/// `line` and `column` are 1-based, a value of 0 means the position is unknown
+/// (e.g. the error was raised by a `TryFrom` impl and never located).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+    pub expected: String,
+}
+
+impl ParseError {
+    pub fn new(expected: impl Into<String>, text: impl Into<String>) -> Self {
+        ParseError {
+            line: 0,
+            column: 0,
+            text: text.into(),
+            expected: expected.into(),
+        }
+    }
+
+    /// Locates the error at the zero-based line `idx` and byte offset `offset`.
+    pub fn at(mut self, idx: usize, offset: usize) -> Self {
+        self.line = idx + 1;
+        self.column = offset + 1;
+        self
+    }
+
+    /// Locates the error on `token`, which is expected to be a slice of `line`.
+    pub fn at_token(self, idx: usize, line: &str, token: &str) -> Self {
+        self.at(idx, offset_of(line, token))
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.line != 0 {
+            write!(f, "line {}, column {}: ", self.line, self.column)?;
+        }
+        write!(f, "expected {}, found `{}`", self.expected, self.text)
+    }
+}
+
+impl Error for ParseError {}
+
+/// Byte offset of `token` inside `line`, or the end of `line` if `token` is not a slice of it.
+fn offset_of(line: &str, token: &str) -> usize {
+    let start = line.as_ptr() as usize;
+    let pos = token.as_ptr() as usize;
+    if pos >= start && pos + token.len() <= start + line.len() {
+        pos - start
+    } else {
+        line.len()
+    }
+}
+
+/// Parses `token`, a slice of the zero-based line `idx`, with `FromStr`.
+pub fn parse<T: FromStr>(idx: usize, line: &str, token: &str, expected: &str) -> Result<T, ParseError> {
+    token.parse().map_err(|_| ParseError::new(expected, token).at_token(idx, line, token))
+}
+
+/// Returns the next token of `line`, or an error pointing at the end of the line.
+pub fn next_token<'a>(tokens: &mut impl Iterator<Item=&'a str>, idx: usize, line: &str, expected: &str) -> Result<&'a str, ParseError> {
+    tokens.next().ok_or_else(|| ParseError::new(expected, "end of line").at(idx, line.len()))
+}
+
+/// Consumes the next token of `line`, which must be exactly `keyword`.
+pub fn expect_token<'a>(tokens: &mut impl Iterator<Item=&'a str>, idx: usize, line: &str, keyword: &str) -> Result<(), ParseError> {
+    let expected = format!("`{}`", keyword);
+    let token = next_token(tokens, idx, line, &expected)?;
+    if token != keyword {
+        return Err(ParseError::new(expected, token).at_token(idx, line, token));
+    }
+    Ok(())
+}
+
+/// Strips `prefix` from `line`, returning the remainder.
+pub fn strip_prefix<'a>(idx: usize, line: &'a str, prefix: &str) -> Result<&'a str, ParseError> {
+    line.strip_prefix(prefix).ok_or_else(|| {
+        let found = line.chars().take(prefix.chars().count()).collect::<String>();
+        ParseError::new(format!("`{}`", prefix), found).at(idx, 0)
+    })
+}
+
+/// Returns the zero-based line `idx` of `lines`, or an "end of input" error.
+pub fn get_line<'a>(lines: &[&'a str], idx: usize, expected: &str) -> Result<&'a str, ParseError> {
+    lines.get(idx).copied().ok_or_else(|| ParseError::new(expected, "end of input").at(idx, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::*;
+
+    #[test]
+    fn display() {
+        let e = ParseError::new("a number", "x").at(2, 4);
+        assert_eq!(e.to_string(), "line 3, column 5: expected a number, found `x`");
+        assert_eq!(ParseError::new("a number", "x").to_string(), "expected a number, found `x`");
+    }
+
+    #[test]
+    fn token_column() {
+        let line = "addx 1a";
+        let token = line.split(' ').nth(1).unwrap();
+        let e = parse::<i8>(0, line, token, "an integer").unwrap_err();
+        assert_eq!((e.line, e.column), (1, 6));
+        assert_eq!(e.text, "1a");
+    }
+
+    #[test]
+    fn missing_token() {
+        let line = "addx";
+        let mut tokens = line.split(' ');
+        tokens.next();
+        let e = next_token(&mut tokens, 3, line, "an operand").unwrap_err();
+        assert_eq!((e.line, e.column), (4, 5));
+    }
+
+    #[test]
+    fn prefix() {
+        assert_eq!(strip_prefix(0, "Monkey 1:", "Monkey ").unwrap(), "1:");
+        let e = strip_prefix(0, "Monk 1:", "Monkey ").unwrap_err();
+        assert_eq!(e.text, "Monk 1:");
+    }
+}